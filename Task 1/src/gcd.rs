@@ -26,9 +26,319 @@ fn calculate_gcd(mut a: i32, mut b: i32) -> i32 {
     a
 }
 
+#[allow(dead_code)]
+/// Returns true if the claimed value is the GCD of a and b, i.e. it divides both values and matches
+/// the GCD of their absolute values. The GCD is computed in i64 so that i32::MIN is handled, and a
+/// negative claim is never the GCD.
+fn verify_gcd(a: i32, b: i32, claimed: i32) -> bool {
+    if claimed < 0 {
+        return false;
+    }
+    if claimed == 0 {
+        return a == 0 && b == 0;
+    }
+
+    a.checked_rem(claimed) == Some(0)
+        && b.checked_rem(claimed) == Some(0)
+        && gcd_array_i64(&[a, b]) == Some(i64::from(claimed))
+}
+
+#[allow(dead_code)]
+/// Returns the earliest day on which all streams coincide, where each stream first occurs on its
+/// offset day and then repeats every interval days. Offsets must be zero or positive and intervals
+/// positive. Returns None if the slices are empty or differ in length, if an input is out
+/// of range, if the streams never coincide or if the result overflows an i32.
+fn earliest_alignment(offsets: &[i32], intervals: &[i32]) -> Option<i32> {
+    if offsets.is_empty() || offsets.len() != intervals.len() {
+        return None;
+    }
+    if offsets.iter().any(|o| *o < 0) || intervals.iter().any(|i| *i <= 0) {
+        return None;
+    }
+
+    // The streams merged so far coincide on day, day + period, day + 2 * period, etc. where period
+    // is the LCM of their intervals. Both are tracked in i64 so that the LCM may outgrow an i32.
+    let (mut day, mut period) = (i64::from(offsets[0]), i64::from(intervals[0]));
+    for (&offset, &interval) in offsets.iter().zip(intervals).skip(1) {
+        let (offset, interval) = (i64::from(offset), i64::from(interval));
+        if day < offset {
+            let steps = (offset - day - 1) / period + 1;
+            day = day.checked_add(steps.checked_mul(period)?)?;
+        }
+
+        // Solve day + k * period == offset (mod interval) for the smallest k >= 0, which only has a
+        // solution if the GCD of period and interval divides the gap between the streams.
+        let gcd = period.gcd(interval);
+        let gap = (offset - day).rem_euclid(interval);
+        if gap % gcd != 0 {
+            return None;
+        }
+        let modulus = interval / gcd;
+        let k = gap / gcd * mod_inverse(period / gcd, modulus) % modulus;
+        day = day.checked_add(k.checked_mul(period)?)?;
+        if day > i64::from(i32::MAX) {
+            return None;
+        }
+
+        period = (period / gcd).checked_mul(interval)?;
+    }
+
+    i32::try_from(day).ok()
+}
+
+/// Returns the inverse of a modulo m using the extended Euclidean algorithm, where a and m are
+/// positive and coprime.
+fn mod_inverse(a: i64, m: i64) -> i64 {
+    let (mut r, mut next_r) = (a % m, m);
+    let (mut t, mut next_t) = (1, 0);
+    while next_r != 0 {
+        let q = r / next_r;
+        (r, next_r) = (next_r, r - q * next_r);
+        (t, next_t) = (next_t, t - q * next_t);
+    }
+
+    t.rem_euclid(m)
+}
+
+#[allow(dead_code)]
+/// Calculates the GCD of the present values in a given Option<i32> array or returns None if no
+/// value is present.
+fn gcd_optional(values: &[Option<i32>]) -> Option<i32> {
+    values.iter().flatten().copied().reduce(calculate_gcd)
+}
+
+#[allow(dead_code)]
+/// Accumulates the LCM of a stream of positive i32 values, stopping safely if it overflows an i64.
+#[derive(Debug, Default)]
+struct LcmAccumulator {
+    current: Option<i64>,
+}
+
+#[allow(dead_code)]
+impl LcmAccumulator {
+    /// Adds a value to the accumulated LCM. Returns an error and leaves the LCM unchanged if the
+    /// value isn't positive or the LCM would overflow.
+    fn push(&mut self, value: i32) -> Result<(), &'static str> {
+        if value <= 0 {
+            return Err("value must be positive");
+        }
+
+        let lcm = match self.current {
+            None => i64::from(value),
+            Some(current) => {
+                // gcd(a, b) == gcd(a % b, b) and a % b fits in an i32.
+                let gcd = calculate_gcd((current % i64::from(value)) as i32, value);
+                (current / i64::from(gcd))
+                    .checked_mul(i64::from(value))
+                    .ok_or("lcm overflowed an i64")?
+            }
+        };
+        self.current = Some(lcm);
+
+        Ok(())
+    }
+
+    /// Returns the accumulated LCM or None if no value has been pushed.
+    fn value(&self) -> Option<i64> {
+        self.current
+    }
+}
+
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+/// Reduces share counts to their simplest proportions by dividing each count by the GCD of all the
+/// counts. The GCD is computed in i64 so that i32::MIN is handled. Returns None if the array is
+/// empty or all the counts are zero.
+///
+/// A portfolio holding 100, 200 and 300 shares of three stocks is weighted 1 : 2 : 3:
+///
+/// ```text
+/// proportion_shares(&[100, 200, 300]) == Some(vec![1, 2, 3])
+/// ```
+fn proportion_shares(shares: &[i32]) -> Option<Vec<i32>> {
+    let gcd = gcd_array_i64(shares)?;
+    if gcd == 0 {
+        return None;
+    }
+
+    shares
+        .iter()
+        .map(|&s| i32::try_from(i64::from(s) / gcd).ok())
+        .collect()
+}
+
+#[allow(dead_code)]
+/// Calculates the GCD of two i32 integer values or returns an error if both values are zero, in
+/// which case the GCD is undefined.
+fn gcd_strict(a: i32, b: i32) -> Result<i32, &'static str> {
+    if a == 0 && b == 0 {
+        return Err("gcd(0, 0) is undefined");
+    }
+
+    Ok(calculate_gcd(a, b))
+}
+
+#[allow(dead_code)]
+/// Returns the length in days of the cycle after which streams repeating every 1 to 7 days all
+/// line up again, i.e. the LCM of the intervals. Returns None if the array is empty or an interval
+/// is out of range.
+fn common_weekday_cycle(intervals: &[i32]) -> Option<i32> {
+    if intervals.is_empty() || intervals.iter().any(|i| !(1..=7).contains(i)) {
+        return None;
+    }
+
+    let mut acc = LcmAccumulator::default();
+    for interval in intervals {
+        acc.push(*interval).ok()?;
+    }
+
+    // The LCM of values up to 7 is at most 420.
+    acc.value().map(|cycle| cycle as i32)
+}
+
+/// The GCD of two integer values of the same width.
+trait Gcd: Copy {
+    fn gcd(self, other: Self) -> Self;
+}
+
+impl Gcd for i32 {
+    fn gcd(self, other: Self) -> Self {
+        calculate_gcd(self, other)
+    }
+}
+
+impl Gcd for i64 {
+    /// Calculates the GCD the same way as calculate_gcd.
+    fn gcd(mut self, mut other: Self) -> Self {
+        if self < other {
+            (self, other) = (other, self);
+        }
+
+        while other != 0 {
+            (other, self) = (self % other, other);
+        }
+
+        self
+    }
+}
+
+#[allow(dead_code)]
+/// Returns the largest monetary unit in cents that divides all the invoice amounts, or None if the
+/// array is empty.
+fn common_invoice_unit(amounts_cents: &[i64]) -> Option<i64> {
+    amounts_cents.iter().copied().reduce(Gcd::gcd).map(i64::abs)
+}
+
+#[allow(dead_code)]
+/// Returns the smallest consistent tick size of the given price increments, i.e. the GCD of their
+/// absolute values. Returns None if the array is empty, all the increments are zero or an increment
+/// is i64::MIN, whose absolute value doesn't fit in an i64.
+fn infer_tick_size(increments: &[i64]) -> Option<i64> {
+    let mut tick: Option<i64> = None;
+    for increment in increments {
+        let increment = increment.checked_abs()?;
+        tick = Some(tick.map_or(increment, |tick| tick.gcd(increment)));
+    }
+
+    match tick {
+        Some(0) | None => None,
+        tick => tick,
+    }
+}
+
+#[allow(dead_code)]
+/// Calculates the GCD of the byte lengths of the given strings, e.g. to choose a column width that
+/// evenly divides them all, or returns None if the array is empty.
+fn gcd_of_lengths(strings: &[&str]) -> Option<i32> {
+    strings.iter().map(|s| s.len() as i32).reduce(calculate_gcd)
+}
+
+#[allow(dead_code)]
+/// Calculates the GCD of a given i32 array by promoting each value to i64, so that i32::MIN can be
+/// negated safely, or returns None if the array is empty.
+fn gcd_array_i64(ints: &[i32]) -> Option<i64> {
+    ints.iter().map(|&i| i64::from(i).abs()).reduce(Gcd::gcd)
+}
+
+#[allow(dead_code)]
+/// Returns the sample rates reduced by their GCD as (up, down) factors, e.g. (160, 147) for 48000 Hz
+/// and 44100 Hz, or (0, 0) if either rate is zero.
+fn resample_ratio(from_hz: i32, to_hz: i32) -> (i32, i32) {
+    if from_hz == 0 || to_hz == 0 {
+        return (0, 0);
+    }
+
+    let gcd = calculate_gcd(from_hz, to_hz);
+    (from_hz / gcd, to_hz / gcd)
+}
+
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+/// Calculates the moving GCD of the values, i.e. the GCD of each run of window consecutive values.
+/// The result has one entry per window, i.e. values.len() - window + 1 entries. If the window is 0
+/// or longer than the array no window can be formed, and the result is a single None.
+fn windowed_gcd(values: &[i32], window: usize) -> Vec<Option<i32>> {
+    if window == 0 || window > values.len() {
+        return vec![None];
+    }
+
+    values.windows(window).map(calculate_gcd_array).collect()
+}
+
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+/// Returns the prime factorization of n as a map from each prime factor to its exponent. The map is
+/// empty for 0 and 1.
+fn prime_factors(mut n: u32) -> std::collections::BTreeMap<u32, u32> {
+    let mut factors = std::collections::BTreeMap::new();
+    if n == 0 {
+        return factors;
+    }
+
+    let mut p = 2;
+    while u64::from(p) * u64::from(p) <= u64::from(n) {
+        while n.is_multiple_of(p) {
+            *factors.entry(p).or_insert(0) += 1;
+            n /= p;
+        }
+        p += 1;
+    }
+    if n > 1 {
+        *factors.entry(n).or_insert(0) += 1;
+    }
+
+    factors
+}
+
+#[allow(dead_code)]
+#[cfg(feature = "std")]
+/// Calculates the GCD of two u32 values the slow way for teaching purposes: by multiplying the
+/// prime powers that both prime factorizations have in common, each with the smaller exponent. The
+/// result is checked against the Euclidean algorithm in debug builds.
+fn gcd_via_factorization(a: u32, b: u32) -> u32 {
+    if a == 0 || b == 0 {
+        return a.max(b);
+    }
+
+    let b_factors = prime_factors(b);
+    let gcd = prime_factors(a)
+        .iter()
+        .filter_map(|(p, exp)| b_factors.get(p).map(|b_exp| p.pow(*exp.min(b_exp))))
+        .product();
+
+    debug_assert_eq!(i64::from(gcd), i64::from(a).gcd(i64::from(b)));
+    gcd
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{calculate_gcd, calculate_gcd_array};
+    use super::{
+        calculate_gcd, calculate_gcd_array, common_invoice_unit, common_weekday_cycle,
+        earliest_alignment, gcd_array_i64, gcd_of_lengths, gcd_optional, gcd_strict,
+        infer_tick_size, resample_ratio, verify_gcd, Gcd, LcmAccumulator,
+    };
+    #[cfg(feature = "std")]
+    use super::{gcd_via_factorization, prime_factors, proportion_shares, windowed_gcd};
 
     #[test]
     fn test_calculate_gcd_array() {
@@ -41,4 +351,207 @@ mod tests {
     fn test_calculate_gcd() {
         assert_eq!(calculate_gcd(11, 22), 11);
     }
+
+    #[test]
+    fn test_calculate_gcd_core_only() {
+        // Only core is used here, so this also runs with `--no-default-features`.
+        const VALUES: [i32; 4] = [4, 64, 32, 120];
+        assert_eq!(calculate_gcd(12, 18), 6);
+        assert_eq!(calculate_gcd_array(&VALUES), Some(4));
+        assert_eq!(12_i64.gcd(18), 6);
+    }
+
+    #[test]
+    fn test_verify_gcd() {
+        assert!(verify_gcd(12, 18, 6));
+        assert!(!verify_gcd(12, 18, 3));
+        assert!(!verify_gcd(12, 18, 5));
+        assert!(!verify_gcd(12, 18, -6));
+        assert!(!verify_gcd(i32::MIN, 0, -1));
+        assert!(verify_gcd(-12, -18, 6));
+        assert!(verify_gcd(-12, 18, 6));
+        assert!(!verify_gcd(-12, -18, 3));
+        assert!(verify_gcd(i32::MIN, -1, 1));
+        assert!(verify_gcd(i32::MIN, 6, 2));
+        assert!(!verify_gcd(i32::MIN, 0, i32::MAX));
+    }
+
+    #[test]
+    fn test_earliest_alignment() {
+        assert_eq!(earliest_alignment(&[0, 1], &[2, 3]), Some(4));
+        assert_eq!(earliest_alignment(&[2, 0, 0], &[3, 4, 1]), Some(8));
+        assert_eq!(earliest_alignment(&[0, 1], &[2, 4]), None);
+        assert_eq!(earliest_alignment(&[0], &[2, 3]), None);
+        assert_eq!(earliest_alignment(&[0, 1], &[2, 0]), None);
+        assert_eq!(earliest_alignment(&[], &[]), None);
+        assert_eq!(
+            earliest_alignment(&[0, 1_500_000_000], &[1, 1]),
+            Some(1_500_000_000)
+        );
+        assert_eq!(
+            earliest_alignment(&[0, 1], &[2, 1_999_999_999]),
+            Some(2_000_000_000)
+        );
+        assert_eq!(earliest_alignment(&[0, 1], &[2, 2_147_483_647]), None);
+    }
+
+    #[test]
+    fn test_gcd_optional() {
+        assert_eq!(gcd_optional(&[Some(12), None, Some(8)]), Some(4));
+        assert_eq!(gcd_optional(&[None, Some(7)]), Some(7));
+        assert_eq!(gcd_optional(&[None, None]), None);
+        assert_eq!(gcd_optional(&[]), None);
+    }
+
+    #[test]
+    fn test_lcm_accumulator() {
+        let mut acc = LcmAccumulator::default();
+        assert_eq!(acc.value(), None);
+        assert_eq!(acc.push(4), Ok(()));
+        assert_eq!(acc.push(6), Ok(()));
+        assert_eq!(acc.value(), Some(12));
+        assert_eq!(acc.push(0), Err("value must be positive"));
+        assert_eq!(acc.value(), Some(12));
+    }
+
+    #[test]
+    fn test_lcm_accumulator_overflow() {
+        let mut acc = LcmAccumulator::default();
+        assert_eq!(acc.push(i32::MAX), Ok(()));
+        assert_eq!(acc.push(i32::MAX - 1), Ok(()));
+        assert_eq!(acc.push(i32::MAX - 2), Err("lcm overflowed an i64"));
+        assert_eq!(
+            acc.value(),
+            Some(i64::from(i32::MAX) * i64::from(i32::MAX - 1))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_proportion_shares() {
+        assert_eq!(proportion_shares(&[100, 200, 300]), Some(vec![1, 2, 3]));
+        assert_eq!(proportion_shares(&[150, 0, 450]), Some(vec![1, 0, 3]));
+        assert_eq!(proportion_shares(&[7]), Some(vec![1]));
+        assert_eq!(proportion_shares(&[i32::MIN]), Some(vec![-1]));
+        assert_eq!(proportion_shares(&[i32::MIN, 1 << 30]), Some(vec![-2, 1]));
+        assert_eq!(proportion_shares(&[0, 0]), None);
+        assert_eq!(proportion_shares(&[]), None);
+    }
+
+    #[test]
+    fn test_gcd_strict() {
+        assert_eq!(gcd_strict(0, 0), Err("gcd(0, 0) is undefined"));
+        assert_eq!(gcd_strict(0, 5), Ok(5));
+        assert_eq!(gcd_strict(11, 22), Ok(11));
+        assert_eq!(calculate_gcd(0, 0), 0);
+    }
+
+    #[test]
+    fn test_common_weekday_cycle() {
+        assert_eq!(common_weekday_cycle(&[2, 3]), Some(6));
+        assert_eq!(common_weekday_cycle(&[7, 2, 1]), Some(14));
+        assert_eq!(common_weekday_cycle(&[1, 2, 3, 4, 5, 6, 7]), Some(420));
+        assert_eq!(common_weekday_cycle(&[0, 2]), None);
+        assert_eq!(common_weekday_cycle(&[8]), None);
+        assert_eq!(common_weekday_cycle(&[]), None);
+    }
+
+    #[test]
+    fn test_gcd_trait() {
+        assert_eq!(11_i32.gcd(22), calculate_gcd(11, 22));
+        assert_eq!(12_i64.gcd(18), 6);
+        assert_eq!(0_i64.gcd(5), 5);
+        assert_eq!(6_000_000_000_i64.gcd(4_000_000_000), 2_000_000_000);
+    }
+
+    #[test]
+    fn test_common_invoice_unit() {
+        assert_eq!(common_invoice_unit(&[250, 500, 750]), Some(250));
+        assert_eq!(common_invoice_unit(&[1999, 500]), Some(1));
+        assert_eq!(common_invoice_unit(&[]), None);
+    }
+
+    #[test]
+    fn test_infer_tick_size() {
+        assert_eq!(infer_tick_size(&[25, 50, 75]), Some(25));
+        assert_eq!(infer_tick_size(&[-25, 0, 75]), Some(25));
+        assert_eq!(infer_tick_size(&[0, 0]), None);
+        assert_eq!(infer_tick_size(&[25, i64::MIN]), None);
+        assert_eq!(infer_tick_size(&[]), None);
+    }
+
+    #[test]
+    fn test_gcd_of_lengths() {
+        assert_eq!(gcd_of_lengths(&["abcd", "abcdabcd"]), Some(4));
+        assert_eq!(gcd_of_lengths(&["abc", "abcdef", "ab"]), Some(1));
+        assert_eq!(gcd_of_lengths(&["é"]), Some(2));
+        assert_eq!(gcd_of_lengths(&[]), None);
+    }
+
+    #[test]
+    fn test_gcd_array_i64() {
+        assert_eq!(gcd_array_i64(&[4, 64, 32, 120]), Some(4));
+        assert_eq!(gcd_array_i64(&[-12, 18]), Some(6));
+        assert_eq!(gcd_array_i64(&[i32::MIN, 0]), Some(2_147_483_648));
+        assert_eq!(gcd_array_i64(&[i32::MIN, i32::MIN]), Some(2_147_483_648));
+        assert_eq!(gcd_array_i64(&[i32::MIN, 6]), Some(2));
+        assert_eq!(gcd_array_i64(&[]), None);
+    }
+
+    #[test]
+    fn test_resample_ratio() {
+        assert_eq!(resample_ratio(48000, 44100), (160, 147));
+        assert_eq!(resample_ratio(44100, 48000), (147, 160));
+        assert_eq!(resample_ratio(16000, 8000), (2, 1));
+        assert_eq!(resample_ratio(0, 44100), (0, 0));
+        assert_eq!(resample_ratio(48000, 0), (0, 0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_windowed_gcd() {
+        assert_eq!(windowed_gcd(&[12, 8, 4], 2), vec![Some(4), Some(4)]);
+        assert_eq!(windowed_gcd(&[12, 18, 9, 6], 3), vec![Some(3), Some(3)]);
+        assert_eq!(windowed_gcd(&[12, 8], 1), vec![Some(12), Some(8)]);
+        assert_eq!(windowed_gcd(&[12, 8], 2), vec![Some(4)]);
+        assert_eq!(windowed_gcd(&[12, 8], 0), vec![None]);
+        assert_eq!(windowed_gcd(&[12, 8], 3), vec![None]);
+        assert_eq!(windowed_gcd(&[], 2), vec![None]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_prime_factors() {
+        assert_eq!(
+            prime_factors(360).into_iter().collect::<Vec<_>>(),
+            vec![(2, 3), (3, 2), (5, 1)]
+        );
+        assert_eq!(
+            prime_factors(97).into_iter().collect::<Vec<_>>(),
+            vec![(97, 1)]
+        );
+        assert!(prime_factors(1).is_empty());
+        assert!(prime_factors(0).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_gcd_via_factorization() {
+        for (a, b) in [
+            (11, 22),
+            (12, 18),
+            (360, 84),
+            (17, 5),
+            (1, 9),
+            (0, 6),
+            (6, 0),
+            (48, 48),
+        ] {
+            assert_eq!(
+                gcd_via_factorization(a, b) as i32,
+                calculate_gcd(a as i32, b as i32)
+            );
+        }
+        assert_eq!(gcd_via_factorization(4_294_967_295, 65_535), 65_535);
+    }
 }
@@ -1,14 +1,300 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter, Result};
+use std::hash::{Hash, Hasher};
 use std::string::ToString;
+use std::sync::LazyLock;
 
+use chrono::{DateTime, Duration, Utc};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
 
-const NOT_AVAILABLE: &str = "Not available";
+pub(crate) const NOT_AVAILABLE: &str = "Not available";
+const REDACTED: &str = "[redacted]";
+/// The json keys of the fields that PartialEq compares, which canonical_string is built from.
+const CANONICAL_KEYS: [&str; 6] = [
+    "type",
+    "addressLineDetail",
+    "cityOrTown",
+    "provinceOrState",
+    "country",
+    "postalCode",
+];
+/// The json keys that to_redacted_value keeps, besides the id. The coordinates are left out as they
+/// locate an address as precisely as its street lines.
+const REDACTED_KEYS: [&str; 9] = [
+    "type",
+    "addressLineDetail",
+    "provinceOrState",
+    "cityOrTown",
+    "country",
+    "postalCode",
+    "suburbOrDistrict",
+    "lastUpdated",
+    "currency",
+];
 
-type ValidationError = &'static str;
+/// The names of the address fields that can be looked up with Address::field, in export order.
+pub const FIELD_NAMES: [&str; 12] = [
+    "id",
+    "type",
+    "line1",
+    "line2",
+    "line3",
+    "suburb",
+    "city",
+    "province",
+    "postal_code",
+    "country_code",
+    "country",
+    "last_updated",
+];
 
-#[derive(Deserialize, Default, Debug)]
+/// The namespace of the UUIDs derived from address content.
+#[cfg(feature = "uuid")]
+const ADDRESS_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_93b7_4d05_8e61_0c5a_f2d9_7b38);
+
+/// The country codes of the countries that require a province by default. The set is built once on
+/// first use.
+static PROVINCE_REQUIRED_COUNTRIES: LazyLock<HashSet<&str>> =
+    LazyLock::new(|| HashSet::from(["ZA"]));
+
+/// The ISO 3166-1 alpha-2 country codes.
+const ISO_COUNTRY_CODES: [&str; 249] = [
+    "AD", "AE", "AF", "AG", "AI", "AL", "AM", "AO", "AQ", "AR", "AS", "AT", "AU", "AW", "AX", "AZ",
+    "BA", "BB", "BD", "BE", "BF", "BG", "BH", "BI", "BJ", "BL", "BM", "BN", "BO", "BQ", "BR", "BS",
+    "BT", "BV", "BW", "BY", "BZ", "CA", "CC", "CD", "CF", "CG", "CH", "CI", "CK", "CL", "CM", "CN",
+    "CO", "CR", "CU", "CV", "CW", "CX", "CY", "CZ", "DE", "DJ", "DK", "DM", "DO", "DZ", "EC", "EE",
+    "EG", "EH", "ER", "ES", "ET", "FI", "FJ", "FK", "FM", "FO", "FR", "GA", "GB", "GD", "GE", "GF",
+    "GG", "GH", "GI", "GL", "GM", "GN", "GP", "GQ", "GR", "GS", "GT", "GU", "GW", "GY", "HK", "HM",
+    "HN", "HR", "HT", "HU", "ID", "IE", "IL", "IM", "IN", "IO", "IQ", "IR", "IS", "IT", "JE", "JM",
+    "JO", "JP", "KE", "KG", "KH", "KI", "KM", "KN", "KP", "KR", "KW", "KY", "KZ", "LA", "LB", "LC",
+    "LI", "LK", "LR", "LS", "LT", "LU", "LV", "LY", "MA", "MC", "MD", "ME", "MF", "MG", "MH", "MK",
+    "ML", "MM", "MN", "MO", "MP", "MQ", "MR", "MS", "MT", "MU", "MV", "MW", "MX", "MY", "MZ", "NA",
+    "NC", "NE", "NF", "NG", "NI", "NL", "NO", "NP", "NR", "NU", "NZ", "OM", "PA", "PE", "PF", "PG",
+    "PH", "PK", "PL", "PM", "PN", "PR", "PS", "PT", "PW", "PY", "QA", "RE", "RO", "RS", "RU", "RW",
+    "SA", "SB", "SC", "SD", "SE", "SG", "SH", "SI", "SJ", "SK", "SL", "SM", "SN", "SO", "SR", "SS",
+    "ST", "SV", "SX", "SY", "SZ", "TC", "TD", "TF", "TG", "TH", "TJ", "TK", "TL", "TM", "TN", "TO",
+    "TR", "TT", "TV", "TW", "TZ", "UA", "UG", "UM", "US", "UY", "UZ", "VA", "VC", "VE", "VG", "VI",
+    "VN", "VU", "WF", "WS", "YE", "YT", "ZA", "ZM", "ZW",
+];
+
+/// The ISO 3166-1 alpha-2 code, alpha-3 code and common name of well known countries.
+const COUNTRY_CODES: [(&str, &str, &str); 10] = [
+    ("ZA", "ZAF", "South Africa"),
+    ("US", "USA", "United States"),
+    ("GB", "GBR", "United Kingdom"),
+    ("CA", "CAN", "Canada"),
+    ("AU", "AUS", "Australia"),
+    ("NL", "NLD", "Netherlands"),
+    ("DE", "DEU", "Germany"),
+    ("FR", "FRA", "France"),
+    ("LB", "LBN", "Lebanon"),
+    ("NA", "NAM", "Namibia"),
+];
+
+/// The provinces, states or governorates of countries with a known subdivision list.
+const COUNTRY_PROVINCES: [(&str, &[&str]); 4] = [
+    (
+        "ZA",
+        &[
+            "Eastern Cape",
+            "Free State",
+            "Gauteng",
+            "KwaZulu-Natal",
+            "Limpopo",
+            "Mpumalanga",
+            "North West",
+            "Northern Cape",
+            "Western Cape",
+        ],
+    ),
+    (
+        "CA",
+        &[
+            "Alberta",
+            "British Columbia",
+            "Manitoba",
+            "New Brunswick",
+            "Newfoundland and Labrador",
+            "Northwest Territories",
+            "Nova Scotia",
+            "Nunavut",
+            "Ontario",
+            "Prince Edward Island",
+            "Quebec",
+            "Saskatchewan",
+            "Yukon",
+        ],
+    ),
+    (
+        "AU",
+        &[
+            "Australian Capital Territory",
+            "New South Wales",
+            "Northern Territory",
+            "Queensland",
+            "South Australia",
+            "Tasmania",
+            "Victoria",
+            "Western Australia",
+        ],
+    ),
+    (
+        "LB",
+        &[
+            "Akkar",
+            "Baalbek-Hermel",
+            "Beirut",
+            "Beqaa",
+            "Keserwan-Jbeil",
+            "Mount Lebanon",
+            "Nabatieh",
+            "North",
+            "South",
+        ],
+    ),
+];
+
+/// The ISO 4217 currency codes used in each country.
+const COUNTRY_CURRENCIES: [(&str, &str); 10] = [
+    ("ZA", "ZAR"),
+    ("US", "USD"),
+    ("GB", "GBP"),
+    ("CA", "CAD"),
+    ("AU", "AUD"),
+    ("NL", "EUR"),
+    ("DE", "EUR"),
+    ("FR", "EUR"),
+    ("LB", "LBP"),
+    ("NA", "NAD"),
+];
+
+/// Postal code formats of countries with a distinctive postal code pattern. Postal codes of other
+/// countries only need to be filled in. The patterns are compiled once on first use.
+static POSTAL_CODE_FORMATS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    [
+        ("ZA", r"^\d{4}$"),
+        ("US", r"^\d{5}(-\d{4})?$"),
+        ("CA", r"^[A-Za-z]\d[A-Za-z] ?\d[A-Za-z]\d$"),
+        ("GB", r"^[A-Za-z]{1,2}\d[A-Za-z\d]? ?\d[A-Za-z]{2}$"),
+        ("NL", r"^\d{4} ?[A-Za-z]{2}$"),
+    ]
+    .into_iter()
+    .map(|(code, pattern)| {
+        (
+            code,
+            Regex::new(pattern).expect("invalid postal code format"),
+        )
+    })
+    .collect()
+});
+
+/// The components of an address that format_localized orders per country.
+#[derive(Clone, Copy)]
+enum AddressPart {
+    Lines,
+    City,
+    Province,
+    PostalCode,
+    Country,
+}
+
+/// The order of the address components of the countries that don't use the Display order.
+const LOCALIZED_ORDERS: [(&str, [AddressPart; 5]); 5] = {
+    use AddressPart::*;
+    [
+        ("DE", [Lines, PostalCode, City, Province, Country]),
+        ("FR", [Lines, PostalCode, City, Province, Country]),
+        ("NL", [Lines, PostalCode, City, Province, Country]),
+        ("CN", [Country, Province, City, Lines, PostalCode]),
+        ("JP", [PostalCode, Province, City, Lines, Country]),
+    ]
+};
+
+/// A reason why an address is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValidationError {
+    MissingProvince,
+    MissingCountry,
+    InvalidLineDetail,
+    InvalidPostalCode,
+    CurrencyCountryMismatch,
+    InsufficientCompleteness,
+    PartialFieldGroup,
+    FieldExceedsDbLimit(&'static str),
+    UnknownCountryCode,
+    FieldRegexMismatch(&'static str),
+    TypeRequirementUnmet(&'static str),
+    ProvinceNotInCountry,
+    StaleRecord,
+}
+
+impl Display for ValidationError {
+    /// Returns a human readable description of the validation error.
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::MissingProvince => write!(f, "You must include a province if your country is ZA"),
+            Self::MissingCountry => write!(f, "You must include a country"),
+            Self::InvalidLineDetail => write!(
+                f,
+                "You must include valid address details (line 1 and/or 2 must be filled in)"
+            ),
+            Self::InvalidPostalCode => write!(f, "You must include a valid postal code"),
+            Self::CurrencyCountryMismatch => write!(f, "You must use the currency of the country"),
+            Self::InsufficientCompleteness => write!(f, "The address is not complete enough"),
+            Self::PartialFieldGroup => {
+                write!(f, "You must fill in all or none of the grouped fields")
+            }
+            Self::FieldExceedsDbLimit(field) => write!(
+                f,
+                "The {} field is longer than its database column allows",
+                field
+            ),
+            Self::UnknownCountryCode => write!(f, "You must include a known country code"),
+            Self::FieldRegexMismatch(field) => write!(
+                f,
+                "The {} field does not match its configured format",
+                field
+            ),
+            Self::TypeRequirementUnmet(requirement) => {
+                write!(f, "The address type requires {}", requirement)
+            }
+            Self::ProvinceNotInCountry => write!(f, "The province must belong to the country"),
+            Self::StaleRecord => write!(f, "The address has not been updated recently enough"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A soft data quality issue. Unlike a validation error, a warning does not make an address invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValidationWarning {
+    CityInStreetLine,
+    SuburbEqualsCity,
+    CountryNameNotTitleCase,
+    UnexpectedUnicode,
+    PossibleMojibake,
+}
+
+impl Display for ValidationWarning {
+    /// Returns a human readable description of the warning.
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::CityInStreetLine => write!(f, "The city is repeated in the address details"),
+            Self::SuburbEqualsCity => write!(f, "The suburb is the same as the city"),
+            Self::CountryNameNotTitleCase => write!(f, "The country name is not title cased"),
+            Self::UnexpectedUnicode => write!(f, "A field contains unexpected characters"),
+            Self::PossibleMojibake => write!(f, "A field looks like double encoded UTF-8"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
 struct CodeAndName {
     #[serde(default)]
     code: String,
@@ -25,28 +311,39 @@ impl Country {
     fn is_valid_country(&self) -> bool {
         !self.name.is_empty()
     }
+
+    /// Returns true if the country code is empty or a known ISO 3166-1 alpha-2 code, ignoring case.
+    fn has_known_country_code(&self) -> bool {
+        self.code.is_empty()
+            || ISO_COUNTRY_CODES
+                .iter()
+                .any(|code| code.eq_ignore_ascii_case(&self.code))
+    }
 }
 
-#[derive(Deserialize, Default, Debug)]
+#[derive(Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
 struct LineDetail {
     #[serde(default)]
     line1: String,
     #[serde(default)]
     line2: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    line3: String,
 }
 
 impl Display for LineDetail {
-    /// Returns a pretty printing string version of the address line details, or "Not available" if
-    /// both lines are empty.
+    /// Returns a pretty printing string version of the address line details, i.e. the non-empty
+    /// lines joined in order, or "Not available" if all the lines are empty.
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        let ml1 = self.line1.is_empty();
-        let ml2 = self.line2.is_empty();
+        let lines: Vec<&str> = [&self.line1, &self.line2, &self.line3]
+            .into_iter()
+            .map(String::as_str)
+            .filter(|line| !line.is_empty())
+            .collect();
 
-        match true {
-            true if ml1 && ml2 => write!(f, "{}", NOT_AVAILABLE),
-            true if ml1 => write!(f, "{}", self.line2),
-            true if ml2 => write!(f, "{}", self.line1),
-            _ => write!(f, "{}, {}", self.line1, self.line2),
+        match lines.is_empty() {
+            true => write!(f, "{}", NOT_AVAILABLE),
+            false => write!(f, "{}", lines.join(", ")),
         }
     }
 }
@@ -54,12 +351,34 @@ impl Display for LineDetail {
 impl LineDetail {
     /// returns true if the address line details are valid, i.e. at least one line is not empty.
     fn is_valid_line_detail(&self) -> bool {
-        !self.line1.is_empty() || !self.line2.is_empty()
+        !self.line1.is_empty() || !self.line2.is_empty() || !self.line3.is_empty()
+    }
+}
+
+/// Options for rendering an address as a string with Address::format_with. The default options
+/// render the address the same way as Display.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddressFormat {
+    /// Renders the country code instead of the country name.
+    pub use_country_code: bool,
+    /// The separator placed between the address fields.
+    pub separator: String,
+    /// Prefixes the address with its type name, e.g. "Physical Address: ".
+    pub include_type: bool,
+}
+
+impl Default for AddressFormat {
+    fn default() -> Self {
+        Self {
+            use_country_code: false,
+            separator: " - ".to_string(),
+            include_type: true,
+        }
     }
 }
 
 #[allow(dead_code)]
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Default, Debug)]
 pub struct Address {
     pub id: String,
     #[serde(rename = "type")]
@@ -76,11 +395,17 @@ pub struct Address {
     city_or_town: String,
     #[serde(rename = "postalCode")]
     postal_code: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     #[serde(rename = "suburbOrDistrict")]
     suburb_or_district: String,
     #[serde(rename = "lastUpdated")]
     last_updated: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    currency: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    longitude: Option<f64>,
 }
 
 impl Display for Address {
@@ -102,6 +427,22 @@ impl Display for Address {
     }
 }
 
+impl PartialEq for Address {
+    /// Compares the fields that identify the location of an address: the address type, line details,
+    /// city, province, country and postal code. The id, suburb, last updated timestamp, currency and
+    /// coordinates are ignored, so the same physical address taken from two systems compares equal.
+    fn eq(&self, other: &Self) -> bool {
+        self.address_type == other.address_type
+            && self.line_detail == other.line_detail
+            && self.city_or_town == other.city_or_town
+            && self.province_or_state == other.province_or_state
+            && self.country == other.country
+            && self.postal_code == other.postal_code
+    }
+}
+
+impl Eq for Address {}
+
 #[allow(dead_code)]
 impl Address {
     /// Checks whether the needed address fields are valid. If a field is not valid a validation
@@ -110,37 +451,663 @@ impl Address {
         let mut errs = Vec::new();
 
         if !self.has_valid_province() {
-            errs.push("You must include a province if your country is ZA");
+            errs.push(ValidationError::MissingProvince);
         }
         if !self.country.is_valid_country() {
-            errs.push("You must include a country");
+            errs.push(ValidationError::MissingCountry);
+        }
+        if !self.country.has_known_country_code() {
+            errs.push(ValidationError::UnknownCountryCode);
         }
         if !self.line_detail.is_valid_line_detail() {
-            errs.push("You must include valid address details (line 1 and/or 2 must be filled in)");
+            errs.push(ValidationError::InvalidLineDetail);
+        }
+        if !self.is_valid_postal_code() {
+            errs.push(ValidationError::InvalidPostalCode);
+        }
+        if !self.has_valid_currency() {
+            errs.push(ValidationError::CurrencyCountryMismatch);
+        }
+
+        errs
+    }
+
+    /// Returns the address type name of the address.
+    pub fn type_name(&self) -> &str {
+        &self.address_type.name
+    }
+
+    /// Returns the country code of the address.
+    pub fn country_code(&self) -> &str {
+        &self.country.code
+    }
+
+    /// Returns the country name of the address.
+    pub fn country_name(&self) -> &str {
+        &self.country.name
+    }
+
+    /// Returns the province or state name of the address.
+    pub fn province_name(&self) -> &str {
+        &self.province_or_state.name
+    }
+
+    /// Returns the city or town of the address.
+    pub fn city_or_town(&self) -> &str {
+        &self.city_or_town
+    }
+
+    /// Returns the postal code of the address.
+    pub fn postal_code(&self) -> &str {
+        &self.postal_code
+    }
+
+    /// Returns the postal code as a number if it consists only of digits and fits in an i32, or
+    /// None otherwise.
+    pub fn numeric_postal_code(&self) -> Option<i32> {
+        match !self.postal_code.is_empty() && self.postal_code.bytes().all(|b| b.is_ascii_digit()) {
+            true => self.postal_code.parse().ok(),
+            false => None,
+        }
+    }
+
+    /// Returns the suburb or district of the address.
+    pub fn suburb_or_district(&self) -> &str {
+        &self.suburb_or_district
+    }
+
+    /// Returns the raw last updated timestamp of the address.
+    pub fn last_updated(&self) -> &str {
+        &self.last_updated
+    }
+
+    /// Returns the last updated timestamp of the address parsed as an RFC 3339 date time, or an
+    /// error if the timestamp is empty or malformed.
+    pub fn last_updated_datetime(&self) -> std::result::Result<DateTime<Utc>, String> {
+        if self.last_updated.is_empty() {
+            return Err("error parsing last updated timestamp: the timestamp is empty".to_string());
+        }
+
+        DateTime::parse_from_rfc3339(&self.last_updated)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|err| format!("error parsing last updated timestamp: {:?}", err))
+    }
+
+    /// Returns a json value of the address in the input schema. Optional fields that are empty are
+    /// left out, the same as with to_json.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).expect("error serializing address")
+    }
+
+    /// Returns a json string of the address in the input schema. Optional fields that are empty are
+    /// left out.
+    pub fn to_json(&self) -> std::result::Result<String, String> {
+        serde_json::to_string(self)
+            .map_err(|err| format!("error serializing json string: {:?}", err))
+    }
+
+    /// Returns a string of the address rendered with the given format options. Empty fields are
+    /// rendered as "Not available".
+    pub fn format_with(&self, fmt: &AddressFormat) -> String {
+        let country = match fmt.use_country_code {
+            true => &self.country.code,
+            false => &self.country.name,
+        };
+        let fields = [
+            self.line_detail.to_string(),
+            Self::str_or(&self.city_or_town, NOT_AVAILABLE).to_string(),
+            Self::str_or(&self.province_or_state.name, NOT_AVAILABLE).to_string(),
+            Self::str_or(&self.postal_code, NOT_AVAILABLE).to_string(),
+            Self::str_or(country, NOT_AVAILABLE).to_string(),
+        ]
+        .join(&fmt.separator);
+
+        match fmt.include_type {
+            true => format!("{}: {}", self.address_type.name, fields),
+            false => fields,
+        }
+    }
+
+    /// Returns a pretty printing string of the address like Display, with the components ordered
+    /// per the convention of the country in LOCALIZED_ORDERS, e.g. the postal code before the city
+    /// for DE. Other countries use the Display order.
+    pub fn format_localized(&self) -> String {
+        use AddressPart::*;
+        let order = LOCALIZED_ORDERS
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(&self.country.code))
+            .map_or(
+                [Lines, City, Province, PostalCode, Country],
+                |(_, order)| *order,
+            );
+        let parts: Vec<String> = order
+            .iter()
+            .map(|part| match part {
+                Lines => self.line_detail.to_string(),
+                City => Self::str_or(&self.city_or_town, NOT_AVAILABLE).to_string(),
+                Province => Self::str_or(&self.province_or_state.name, NOT_AVAILABLE).to_string(),
+                PostalCode => Self::str_or(&self.postal_code, NOT_AVAILABLE).to_string(),
+                Country => Self::str_or(&self.country.name, NOT_AVAILABLE).to_string(),
+            })
+            .collect();
+
+        format!("{}: {}", self.address_type.name, parts.join(" - "))
+    }
+
+    /// Returns the latitude and longitude of the address if both are present.
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    /// Replaces an alpha-3 country code, or a country name stored as the code, with the alpha-2 code
+    /// of the country. If the code is empty the country name is used instead. Returns true if the
+    /// code was changed.
+    pub fn canonicalize_country_code(&mut self) -> bool {
+        let code = match self.country.code.as_str() {
+            "" => &self.country.name,
+            code => code,
+        };
+        let canonical = COUNTRY_CODES.iter().find(|(alpha2, alpha3, name)| {
+            [alpha2, alpha3, name]
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(code))
+        });
+
+        match canonical {
+            Some((alpha2, _, _)) if *alpha2 != self.country.code => {
+                self.country.code = alpha2.to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Left pads a numeric postal code with zeros to the given width. Non-numeric postal codes and
+    /// postal codes that are already wide enough are left untouched.
+    pub fn pad_postal_code(&mut self, width: usize) {
+        if self.numeric_postal_code().is_some() {
+            self.postal_code = format!("{:0>width$}", self.postal_code);
+        }
+    }
+
+    /// Trims and collapses runs of whitespace into a single space in the address type, line details,
+    /// suburb, city, province, postal code and country, and uppercases the province and country
+    /// codes.
+    pub fn normalize(&mut self) {
+        let collapse = |s: &mut String| *s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        [
+            &mut self.address_type.name,
+            &mut self.line_detail.line1,
+            &mut self.line_detail.line2,
+            &mut self.line_detail.line3,
+            &mut self.suburb_or_district,
+            &mut self.city_or_town,
+            &mut self.province_or_state.name,
+            &mut self.postal_code,
+            &mut self.country.name,
+        ]
+        .into_iter()
+        .for_each(collapse);
+        [&mut self.province_or_state.code, &mut self.country.code]
+            .into_iter()
+            .for_each(|code| *code = code.trim().to_uppercase());
+    }
+
+    /// Swaps the address lines if they look reversed, i.e. line 2 starts with a digit like a street
+    /// number line and line 1 doesn't.
+    pub fn normalize_line_order(&mut self) {
+        let starts_with_digit = |line: &str| line.starts_with(|c: char| c.is_ascii_digit());
+        if starts_with_digit(&self.line_detail.line2) && !starts_with_digit(&self.line_detail.line1)
+        {
+            std::mem::swap(&mut self.line_detail.line1, &mut self.line_detail.line2);
+        }
+    }
+
+    /// Returns the value of the field with the given name (see FIELD_NAMES) or None if there is no
+    /// such field.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        let value = match name {
+            "id" => &self.id,
+            "type" => &self.address_type.name,
+            "line1" => &self.line_detail.line1,
+            "line2" => &self.line_detail.line2,
+            "line3" => &self.line_detail.line3,
+            "suburb" => &self.suburb_or_district,
+            "city" => &self.city_or_town,
+            "province" => &self.province_or_state.name,
+            "postal_code" => &self.postal_code,
+            "country_code" => &self.country.code,
+            "country" => &self.country.name,
+            "last_updated" => &self.last_updated,
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Returns a canonical json string of the address location, i.e. the fields in CANONICAL_KEYS
+    /// that PartialEq compares, with the keys in sorted order. Addresses that compare equal have the
+    /// same canonical string. The string follows the to_value schema, so changing how addresses
+    /// serialize also changes their fingerprints and deterministic ids.
+    pub fn canonical_string(&self) -> String {
+        let mut value = self.to_value();
+        if let Some(obj) = value.as_object_mut() {
+            obj.retain(|key, _| CANONICAL_KEYS.contains(&key.as_str()));
+        }
+        value.to_string()
+    }
+
+    /// Returns a hash of the canonical string of the address, so that addresses with the same
+    /// location have the same fingerprint. The hash is only stable within one process, so
+    /// fingerprints must not be stored or compared across runs.
+    pub(crate) fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.canonical_string().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns a UUIDv5 derived from the canonical string of the address, so that addresses with the
+    /// same location always get the same id.
+    #[cfg(feature = "uuid")]
+    pub fn deterministic_id(&self) -> String {
+        Uuid::new_v5(&ADDRESS_NAMESPACE, self.canonical_string().as_bytes()).to_string()
+    }
+
+    /// Returns a MECARD string of the address for encoding in a QR code, using the address type as
+    /// the card name. The address fields are ordered as post office box (always empty), extended
+    /// address (line 2), street (line 1), city, province, postal code and country.
+    pub fn to_qr_payload(&self) -> String {
+        let escape = |s: &str| {
+            s.chars().fold(String::new(), |mut out, c| {
+                if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+                    out.push('\\');
+                }
+                out.push(c);
+                out
+            })
+        };
+        let adr = [
+            "",
+            &self.line_detail.line2,
+            &self.line_detail.line1,
+            &self.city_or_town,
+            &self.province_or_state.name,
+            &self.postal_code,
+            &self.country.name,
+        ]
+        .map(escape)
+        .join(",");
+
+        format!("MECARD:N:{};ADR:{};;", escape(&self.address_type.name), adr)
+    }
+
+    /// Returns a json value of the address in the input schema, with the address lines masked, the
+    /// id replaced by the given opaque token and only the fields in REDACTED_KEYS kept.
+    pub fn to_redacted_value(&self, token: &str) -> Value {
+        let mask = |line: &str| match line {
+            "" => "",
+            _ => REDACTED,
+        };
+        let mut value = self.to_value();
+        if let Some(obj) = value.as_object_mut() {
+            obj.retain(|key, _| REDACTED_KEYS.contains(&key.as_str()));
+        }
+        value["id"] = json!(token);
+        value["addressLineDetail"]["line1"] = json!(mask(&self.line_detail.line1));
+        value["addressLineDetail"]["line2"] = json!(mask(&self.line_detail.line2));
+        if !self.line_detail.line3.is_empty() {
+            value["addressLineDetail"]["line3"] = json!(REDACTED);
+        }
+        value
+    }
+
+    /// Checks the address fields like validate and also adds a validation error if the completeness
+    /// score of the address is below the minimum score.
+    pub fn validate_min_completeness(&self, min_score: u8) -> Vec<ValidationError> {
+        let mut errs = self.validate();
+
+        if self.completeness_score() < min_score {
+            errs.push(ValidationError::InsufficientCompleteness);
+        }
+
+        errs
+    }
+
+    /// Checks that the named fields (see FIELD_NAMES) are either all filled in or all empty, adding a
+    /// validation error if only some are filled in. Returns an error if a field name is unknown.
+    pub fn validate_field_group_consistency(
+        &self,
+        group: &[&str],
+    ) -> std::result::Result<Vec<ValidationError>, String> {
+        if let Some(unknown) = group.iter().find(|name| !FIELD_NAMES.contains(name)) {
+            return Err(format!("unknown field: {}", unknown));
         }
-        if !Self::is_valid_postal_code(&self.postal_code) {
-            errs.push("You must include a valid postal code");
+
+        let mut errs = Vec::new();
+
+        let filled = group
+            .iter()
+            .filter(|name| self.field(name).is_some_and(|value| !value.is_empty()))
+            .count();
+        if filled > 0 && filled < group.len() {
+            errs.push(ValidationError::PartialFieldGroup);
+        }
+
+        Ok(errs)
+    }
+
+    /// Checks the length in characters of each field (see FIELD_NAMES) against its configured
+    /// database column limit, adding a validation error naming each field that exceeds it. Fields
+    /// without a limit are not checked.
+    pub fn validate_db_limits(&self, limits: &HashMap<&str, usize>) -> Vec<ValidationError> {
+        FIELD_NAMES
+            .iter()
+            .filter(|name| {
+                limits.get(*name).is_some_and(|limit| {
+                    self.field(name)
+                        .is_some_and(|value| value.chars().count() > *limit)
+                })
+            })
+            .map(|name| ValidationError::FieldExceedsDbLimit(name))
+            .collect()
+    }
+
+    /// Checks each non-empty field (see FIELD_NAMES) against its configured regex, adding a
+    /// validation error naming each field that does not match. Fields without a rule are not
+    /// checked.
+    pub fn validate_with_field_regex(&self, rules: &HashMap<&str, Regex>) -> Vec<ValidationError> {
+        FIELD_NAMES
+            .iter()
+            .filter(|name| {
+                rules.get(*name).is_some_and(|rule| {
+                    self.field(name)
+                        .is_some_and(|value| !value.is_empty() && !rule.is_match(value))
+                })
+            })
+            .map(|name| ValidationError::FieldRegexMismatch(name))
+            .collect()
+    }
+
+    /// Checks the fields that the address type requires: a postal address must have a postal code
+    /// and a physical address must have address line details. Type names are matched ignoring
+    /// case and other types have no requirements.
+    pub fn validate_type_field_requirements(&self) -> Vec<ValidationError> {
+        let type_name = self.address_type.name.to_lowercase();
+        let mut errs = Vec::new();
+
+        if type_name == "postal address" && self.postal_code.is_empty() {
+            errs.push(ValidationError::TypeRequirementUnmet("a postal code"));
+        }
+        if type_name == "physical address" && !self.line_detail.is_valid_line_detail() {
+            errs.push(ValidationError::TypeRequirementUnmet(
+                "address line details",
+            ));
         }
 
         errs
     }
 
+    /// Checks that a filled in province belongs to the country according to COUNTRY_PROVINCES,
+    /// ignoring case. Countries without a known province list are not checked.
+    pub fn validate_province_in_country(&self) -> Vec<ValidationError> {
+        let province = &self.province_or_state.name;
+        match COUNTRY_PROVINCES
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(&self.country.code))
+        {
+            Some((_, provinces))
+                if !province.is_empty()
+                    && !provinces.iter().any(|p| p.eq_ignore_ascii_case(province)) =>
+            {
+                vec![ValidationError::ProvinceNotInCountry]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Adds a validation error if the address was last updated more than the maximum number of days
+    /// before now. Addresses with an unparseable timestamp are not checked, and a maximum age outside
+    /// the range of a duration never makes an address stale.
+    pub fn validate_freshness(
+        &self,
+        now: DateTime<Utc>,
+        max_age_days: i64,
+    ) -> Vec<ValidationError> {
+        match self.last_updated_datetime() {
+            Ok(updated)
+                if Duration::try_days(max_age_days)
+                    .is_some_and(|max_age| now - updated > max_age) =>
+            {
+                vec![ValidationError::StaleRecord]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the percentage (0 to 100) of the address type, address line details, suburb, city,
+    /// province, postal code and country that are filled in.
+    pub fn completeness_score(&self) -> u8 {
+        let filled = [
+            !self.address_type.name.is_empty(),
+            self.line_detail.is_valid_line_detail(),
+            !self.suburb_or_district.is_empty(),
+            !self.city_or_town.is_empty(),
+            !self.province_or_state.name.is_empty(),
+            !self.postal_code.is_empty(),
+            !self.country.name.is_empty(),
+        ];
+
+        (filled.iter().filter(|f| **f).count() * 100 / filled.len()) as u8
+    }
+
+    /// Checks the address for soft data quality issues that don't make it invalid. If an issue is
+    /// found a warning is added to a warning vector.
+    pub fn warnings(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        if self.has_city_in_line_detail() {
+            warnings.push(ValidationWarning::CityInStreetLine);
+        }
+        if self.has_suburb_equal_to_city() {
+            warnings.push(ValidationWarning::SuburbEqualsCity);
+        }
+        if self.country.name != Self::title_case(&self.country.name) {
+            warnings.push(ValidationWarning::CountryNameNotTitleCase);
+        }
+        if self.has_unexpected_unicode() {
+            warnings.push(ValidationWarning::UnexpectedUnicode);
+        }
+        if self.has_possible_mojibake() {
+            warnings.push(ValidationWarning::PossibleMojibake);
+        }
+
+        warnings
+    }
+
+    /// Returns true if the city is repeated (case-insensitively) in any address line.
+    fn has_city_in_line_detail(&self) -> bool {
+        if self.city_or_town.is_empty() {
+            return false;
+        }
+
+        let city = self.city_or_town.to_lowercase();
+        self.line_detail.line1.to_lowercase().contains(&city)
+            || self.line_detail.line2.to_lowercase().contains(&city)
+            || self.line_detail.line3.to_lowercase().contains(&city)
+    }
+
+    /// Returns true if a text field contains a character other than a letter, digit, space or common
+    /// punctuation mark, e.g. an emoji or a right-to-left mark.
+    fn has_unexpected_unicode(&self) -> bool {
+        let expected = |c: char| {
+            c.is_alphanumeric() || c == ' ' || c.is_ascii_punctuation() || "‘’“”–—".contains(c)
+        };
+        self.text_fields()
+            .iter()
+            .any(|field| !field.chars().all(expected))
+    }
+
+    /// Returns true if a text field contains a telltale sign of UTF-8 that was decoded as Latin-1
+    /// or Windows-1252, i.e. "Ã" or "Â" followed by a character that stands for a continuation byte,
+    /// like "Ã©" instead of "é".
+    fn has_possible_mojibake(&self) -> bool {
+        let continuation = |c: char| {
+            ('\u{80}'..='\u{BF}').contains(&c) || "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ".contains(c)
+        };
+        self.text_fields().iter().any(|field| {
+            field
+                .chars()
+                .zip(field.chars().skip(1))
+                .any(|(c, next)| (c == 'Ã' || c == 'Â') && continuation(next))
+        })
+    }
+
+    /// Returns the free text fields of the address.
+    fn text_fields(&self) -> [&str; 9] {
+        [
+            &self.address_type.name,
+            &self.line_detail.line1,
+            &self.line_detail.line2,
+            &self.line_detail.line3,
+            &self.suburb_or_district,
+            &self.city_or_town,
+            &self.province_or_state.name,
+            &self.postal_code,
+            &self.country.name,
+        ]
+    }
+
+    /// Returns true if the address has a province but no valid country, which makes the province
+    /// ambiguous.
+    pub fn has_province_without_country(&self) -> bool {
+        !self.province_or_state.name.is_empty() && !self.country.is_valid_country()
+    }
+
+    /// Returns true if the suburb is filled in and the same (case-insensitively) as the city.
+    fn has_suburb_equal_to_city(&self) -> bool {
+        !self.suburb_or_district.is_empty()
+            && self.suburb_or_district.to_lowercase() == self.city_or_town.to_lowercase()
+    }
+
     /// The solution to d.
-    fn is_valid(&self) -> bool {
-        self.has_valid_province()
-            && self.country.is_valid_country()
-            && self.line_detail.is_valid_line_detail()
-            && Self::is_valid_postal_code(&self.postal_code)
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_empty()
     }
 
-    /// Returns true if the address has a valid province.
+    /// Returns true if the address has a valid province, i.e. the province is filled in if the
+    /// country code is in PROVINCE_REQUIRED_COUNTRIES in any case.
     fn has_valid_province(&self) -> bool {
-        match self.country.code.as_str() {
-            "ZA" => !self.province_or_state.name.to_string().is_empty(),
-            _ => true,
+        self.has_valid_province_with(&PROVINCE_REQUIRED_COUNTRIES)
+    }
+
+    /// Returns true if the province is filled in or the country code, uppercased, is not in the
+    /// given set of country codes that require a province.
+    pub fn has_valid_province_with(&self, required: &HashSet<&str>) -> bool {
+        !required.contains(self.country.code.to_uppercase().as_str())
+            || !self.province_or_state.name.is_empty()
+    }
+
+    /// Returns how similar the address is to another address, from 0.0 for nothing in common to 1.0
+    /// for the same location. The score is the average word overlap of the address lines, city,
+    /// province, postal code and country, ignoring case and punctuation.
+    pub fn similarity(&self, other: &Address) -> f64 {
+        let lines = format!(
+            "{} {} {}",
+            self.line_detail.line1, self.line_detail.line2, self.line_detail.line3
+        );
+        let other_lines = format!(
+            "{} {} {}",
+            other.line_detail.line1, other.line_detail.line2, other.line_detail.line3
+        );
+        let fields = [
+            (lines.as_str(), other_lines.as_str()),
+            (&self.city_or_town, &other.city_or_town),
+            (&self.province_or_state.name, &other.province_or_state.name),
+            (&self.postal_code, &other.postal_code),
+            (&self.country.name, &other.country.name),
+        ];
+
+        fields
+            .iter()
+            .map(|(a, b)| Self::word_overlap(a, b))
+            .sum::<f64>()
+            / fields.len() as f64
+    }
+
+    /// Merges the address with another record of it, taking each field from the more recently
+    /// updated record unless it is empty there. Timestamps that can't be parsed are compared as
+    /// strings.
+    pub fn coalesce(self, other: Address) -> Address {
+        let other_is_newer = match (self.last_updated_datetime(), other.last_updated_datetime()) {
+            (Ok(dt), Ok(other_dt)) => other_dt > dt,
+            _ => other.last_updated > self.last_updated,
+        };
+        let (newer, older) = match other_is_newer {
+            true => (other, self),
+            false => (self, other),
+        };
+        let pick = |newer: String, older: String| match newer.is_empty() {
+            true => older,
+            false => newer,
+        };
+        let pick_code_and_name =
+            |newer: CodeAndName, older: CodeAndName| match newer.name.is_empty() {
+                true => older,
+                false => newer,
+            };
+
+        Address {
+            id: pick(newer.id, older.id),
+            address_type: pick_code_and_name(newer.address_type, older.address_type),
+            line_detail: match newer.line_detail.is_valid_line_detail() {
+                true => newer.line_detail,
+                false => older.line_detail,
+            },
+            province_or_state: pick_code_and_name(newer.province_or_state, older.province_or_state),
+            country: pick_code_and_name(newer.country, older.country),
+            city_or_town: pick(newer.city_or_town, older.city_or_town),
+            postal_code: pick(newer.postal_code, older.postal_code),
+            suburb_or_district: pick(newer.suburb_or_district, older.suburb_or_district),
+            last_updated: pick(newer.last_updated, older.last_updated),
+            currency: pick(newer.currency, older.currency),
+            latitude: newer.latitude.or(older.latitude),
+            longitude: newer.longitude.or(older.longitude),
+        }
+    }
+
+    /// Returns the code of the country implied by the postal code format if it conflicts with the
+    /// recorded country, i.e. the postal code doesn't fit the format of the recorded country but fits
+    /// the format of exactly one other country. Countries without a known format are skipped.
+    pub fn conflicting_postal_country(&self) -> Option<&'static str> {
+        let (_, format) = POSTAL_CODE_FORMATS
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(&self.country.code))?;
+        if format.is_match(&self.postal_code) {
+            return None;
+        }
+
+        let mut implied = POSTAL_CODE_FORMATS
+            .iter()
+            .filter(|(_, format)| format.is_match(&self.postal_code))
+            .map(|(code, _)| *code);
+        match (implied.next(), implied.next()) {
+            (Some(code), None) => Some(code),
+            _ => None,
         }
     }
 
+    /// Returns true if the address has no currency, the country's currency is unknown, or the
+    /// currency matches the currency of the country.
+    fn has_valid_currency(&self) -> bool {
+        if self.currency.is_empty() {
+            return true;
+        }
+
+        COUNTRY_CURRENCIES
+            .iter()
+            .find(|(country, _)| country.eq_ignore_ascii_case(&self.country.code))
+            .is_none_or(|(_, currency)| currency.eq_ignore_ascii_case(&self.currency))
+    }
+
     /// Returns the input string literal if it is not empty otherwise a default value.
     fn str_or<'a>(s: &'a str, default: &'a str) -> &'a str {
         match s {
@@ -149,20 +1116,201 @@ impl Address {
         }
     }
 
-    /// Returns true if the postal code is a numeric value.
-    fn is_valid_postal_code(s: &str) -> bool {
-        match Regex::new(r"^\d+$") {
-            Ok(r) => r.is_match(s),
-            _ => false,
+    /// Returns the string with the first letter of every word uppercased and the rest lowercased.
+    fn title_case(s: &str) -> String {
+        s.split(' ')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect(),
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Returns the number of shared lowercase words in two strings divided by the number of distinct
+    /// words in both. Two strings without any words are treated as identical.
+    fn word_overlap(a: &str, b: &str) -> f64 {
+        let words = |s: &str| -> HashSet<String> {
+            s.split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(str::to_lowercase)
+                .collect()
+        };
+        let (a, b) = (words(a), words(b));
+        if a.is_empty() && b.is_empty() {
+            return 1.0;
+        }
+
+        a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+    }
+
+    /// Returns true if the postal code matches the postal code format of the country, or is filled
+    /// in if the country has no known format.
+    fn is_valid_postal_code(&self) -> bool {
+        match POSTAL_CODE_FORMATS
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(&self.country.code))
+        {
+            Some((_, format)) => format.is_match(&self.postal_code),
+            None => !self.postal_code.is_empty(),
+        }
+    }
+}
+
+/// A builder for creating an address in code rather than from json. Every field starts out empty.
+#[derive(Default, Debug)]
+pub struct AddressBuilder {
+    address: Address,
+}
+
+impl AddressBuilder {
+    /// Creates a builder for an empty address.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the id of the address.
+    pub fn id(mut self, id: &str) -> Self {
+        self.address.id = id.to_string();
+        self
+    }
+
+    /// Sets the address type code and name, e.g. "1" and "Physical Address".
+    pub fn address_type(mut self, code: &str, name: &str) -> Self {
+        self.address.address_type = Type {
+            code: code.to_string(),
+            name: name.to_string(),
+        };
+        self
+    }
+
+    /// Sets the first address line.
+    pub fn line1(mut self, line1: &str) -> Self {
+        self.address.line_detail.line1 = line1.to_string();
+        self
+    }
+
+    /// Sets the second address line.
+    pub fn line2(mut self, line2: &str) -> Self {
+        self.address.line_detail.line2 = line2.to_string();
+        self
+    }
+
+    /// Sets the third address line, e.g. a unit, suite or attention line.
+    pub fn line3(mut self, line3: &str) -> Self {
+        self.address.line_detail.line3 = line3.to_string();
+        self
+    }
+
+    /// Sets the suburb or district.
+    pub fn suburb_or_district(mut self, suburb: &str) -> Self {
+        self.address.suburb_or_district = suburb.to_string();
+        self
+    }
+
+    /// Sets the city or town.
+    pub fn city_or_town(mut self, city: &str) -> Self {
+        self.address.city_or_town = city.to_string();
+        self
+    }
+
+    /// Sets the province or state code and name.
+    pub fn province(mut self, code: &str, name: &str) -> Self {
+        self.address.province_or_state = ProvinceOrState {
+            code: code.to_string(),
+            name: name.to_string(),
+        };
+        self
+    }
+
+    /// Sets the country code, e.g. "ZA".
+    pub fn country_code(mut self, code: &str) -> Self {
+        self.address.country.code = code.to_string();
+        self
+    }
+
+    /// Sets the country name, e.g. "South Africa".
+    pub fn country_name(mut self, name: &str) -> Self {
+        self.address.country.name = name.to_string();
+        self
+    }
+
+    /// Sets the postal code.
+    pub fn postal_code(mut self, postal_code: &str) -> Self {
+        self.address.postal_code = postal_code.to_string();
+        self
+    }
+
+    /// Sets the last updated timestamp, e.g. "2015-06-21T00:00:00.000Z".
+    pub fn last_updated(mut self, last_updated: &str) -> Self {
+        self.address.last_updated = last_updated.to_string();
+        self
+    }
+
+    /// Sets the ISO 4217 currency code.
+    pub fn currency(mut self, currency: &str) -> Self {
+        self.address.currency = currency.to_string();
+        self
+    }
+
+    /// Sets the latitude and longitude.
+    pub fn coordinates(mut self, latitude: f64, longitude: f64) -> Self {
+        self.address.latitude = Some(latitude);
+        self.address.longitude = Some(longitude);
+        self
+    }
+
+    /// Returns the address if it passes validation, or the validation errors otherwise.
+    pub fn build(self) -> std::result::Result<Address, Vec<ValidationError>> {
+        match self.address.validate() {
+            errs if errs.is_empty() => Ok(self.address),
+            errs => Err(errs),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Address {
+    /// Creates a valid South African address with the given json fields overridden. Used as a helper
+    /// function for unit tests.
+    pub fn with_overrides(overrides: serde_json::Value) -> Self {
+        let mut value = serde_json::json!({
+            "id": "1",
+            "type": { "code": "1", "name": "Physical Address" },
+            "addressLineDetail": { "line1": "Address 1", "line2": "Line 2" },
+            "provinceOrState": { "code": "5", "name": "Eastern Cape" },
+            "cityOrTown": "City 1",
+            "country": { "code": "ZA", "name": "South Africa" },
+            "postalCode": "1234",
+            "lastUpdated": "2015-06-21T00:00:00.000Z"
+        });
+        if let (Some(base), Some(overrides)) = (value.as_object_mut(), overrides.as_object()) {
+            base.extend(overrides.clone());
         }
+        serde_json::from_value(value).expect("error deserializing test address")
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use chrono::{TimeZone, Utc};
+    use regex::Regex;
+    use serde_json::json;
+
     use crate::addresses::Addresses;
 
-    use super::{Address, ValidationError, NOT_AVAILABLE};
+    use super::{
+        Address, AddressBuilder, AddressFormat, ValidationError, ValidationWarning, FIELD_NAMES,
+        NOT_AVAILABLE, REDACTED,
+    };
 
     #[test]
     fn test_is_valid_line_detail() {
@@ -170,6 +1318,169 @@ mod tests {
             assert!(addrs.addresses[0].line_detail.is_valid_line_detail());
             assert!(!addrs.addresses[1].line_detail.is_valid_line_detail());
             assert!(addrs.addresses[2].line_detail.is_valid_line_detail());
+        });
+
+        let addr = Address::with_overrides(json!({ "addressLineDetail": { "line3": "Unit 4" } }));
+        assert!(addr.line_detail.is_valid_line_detail());
+    }
+
+    #[test]
+    fn test_address_builder() {
+        let base = || {
+            AddressBuilder::new()
+                .id("1")
+                .address_type("1", "Physical Address")
+                .line1("Address 1")
+                .line2("Line 2")
+                .city_or_town("City 1")
+                .country_code("ZA")
+                .country_name("South Africa")
+                .postal_code("1234")
+                .last_updated("2015-06-21T00:00:00.000Z")
+        };
+
+        assert_eq!(
+            base().build().unwrap_err(),
+            vec![ValidationError::MissingProvince]
+        );
+
+        let addr = base()
+            .province("5", "Eastern Cape")
+            .build()
+            .expect("error building address");
+        assert_eq!(
+            addr.to_value(),
+            Address::with_overrides(json!({})).to_value()
+        );
+    }
+
+    #[test]
+    fn test_format_with() {
+        Addresses::with_addresses(|addrs| {
+            addrs.addresses.iter().for_each(|addr| {
+                assert_eq!(
+                    addr.format_with(&AddressFormat::default()),
+                    addr.to_string()
+                );
+            })
+        });
+
+        let compact = AddressFormat {
+            use_country_code: true,
+            separator: ", ".to_string(),
+            include_type: false,
+        };
+        let addr = Address::with_overrides(json!({}));
+        assert_eq!(
+            addr.format_with(&compact),
+            "Address 1, Line 2, City 1, Eastern Cape, 1234, ZA"
+        );
+        let addr = Address::with_overrides(json!({ "country": { "name": "South Africa" } }));
+        assert_eq!(
+            addr.format_with(&compact),
+            "Address 1, Line 2, City 1, Eastern Cape, 1234, Not available"
+        );
+    }
+
+    #[test]
+    fn test_format_localized() {
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "Unter den Linden 1" },
+            "cityOrTown": "Berlin",
+            "provinceOrState": { "name": "Berlin" },
+            "country": { "code": "DE", "name": "Germany" },
+            "postalCode": "10117",
+        }));
+        assert_eq!(
+            addr.format_localized(),
+            "Physical Address: Unter den Linden 1 - 10117 - Berlin - Berlin - Germany"
+        );
+
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "1-1 Chiyoda" },
+            "cityOrTown": "Chiyoda",
+            "provinceOrState": { "name": "Tokyo" },
+            "country": { "code": "jp", "name": "Japan" },
+            "postalCode": "100-0001",
+        }));
+        assert_eq!(
+            addr.format_localized(),
+            "Physical Address: 100-0001 - Tokyo - Chiyoda - 1-1 Chiyoda - Japan"
+        );
+
+        Addresses::with_addresses(|addrs| {
+            addrs
+                .addresses
+                .iter()
+                .for_each(|addr| assert_eq!(addr.format_localized(), addr.to_string()))
+        });
+    }
+
+    #[test]
+    fn test_to_qr_payload() {
+        let addr = Address::with_overrides(json!({}));
+        assert_eq!(
+            addr.to_qr_payload(),
+            "MECARD:N:Physical Address;ADR:,Line 2,Address 1,City 1,Eastern Cape,1234,South Africa;;"
+        );
+
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "Unit 4; Block B", "line2": "" },
+            "cityOrTown": "Cape Town, CBD",
+        }));
+        let payload = addr.to_qr_payload();
+        assert!(payload.contains("Unit 4\\; Block B"));
+        assert!(payload.contains("Cape Town\\, CBD"));
+        assert!(payload.contains("South Africa"));
+    }
+
+    #[test]
+    fn test_partial_eq_for_address() {
+        let addr = Address::with_overrides(json!({}));
+        let other = Address::with_overrides(json!({
+            "id": "99",
+            "lastUpdated": "2020-01-01T00:00:00.000Z",
+        }));
+        assert_eq!(addr, other);
+
+        let other = Address::with_overrides(json!({ "postalCode": "4321" }));
+        assert_ne!(addr, other);
+    }
+
+    #[test]
+    fn test_last_updated_datetime() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.addresses[0].last_updated_datetime(),
+                Ok(Utc.with_ymd_and_hms(2015, 6, 21, 0, 0, 0).unwrap())
+            );
+        });
+
+        let addr = Address::with_overrides(json!({ "lastUpdated": "" }));
+        assert_eq!(
+            addr.last_updated_datetime(),
+            Err("error parsing last updated timestamp: the timestamp is empty".to_string())
+        );
+        let addr = Address::with_overrides(json!({ "lastUpdated": "21 June 2015" }));
+        assert!(addr
+            .last_updated_datetime()
+            .is_err_and(|err| err.starts_with("error parsing last updated timestamp: ")));
+    }
+
+    #[test]
+    fn test_to_json_round_trip() {
+        let data = std::fs::read_to_string("src/addresses.json").expect("error reading addresses");
+        let raw: Vec<serde_json::Value> = serde_json::from_str(&data).expect("error parsing json");
+
+        Addresses::with_addresses(|addrs| {
+            let json = addrs.addresses[0]
+                .to_json()
+                .expect("error serializing address");
+            let value: serde_json::Value = serde_json::from_str(&json).expect("error parsing json");
+            assert_eq!(value, raw[0]);
+
+            let back: Address = serde_json::from_str(&json).expect("error deserializing address");
+            assert_eq!(format!("{}", back), format!("{}", addrs.addresses[0]));
         })
     }
 
@@ -182,7 +1493,31 @@ mod tests {
             );
             assert_eq!(format!("{}", addrs.addresses[1].line_detail), NOT_AVAILABLE);
             assert_eq!(format!("{}", addrs.addresses[2].line_detail), "Address 3");
-        })
+        });
+
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "", "line2": "Line 2", "line3": "Attn: Accounts" },
+        }));
+        assert_eq!(format!("{}", addr.line_detail), "Line 2, Attn: Accounts");
+    }
+
+    #[test]
+    fn test_display_for_line_detail_with_line3() {
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "12 Long Street", "line2": "Suite 5", "line3": "Unit 4" },
+        }));
+        assert_eq!(
+            format!("{}", addr.line_detail),
+            "12 Long Street, Suite 5, Unit 4"
+        );
+        assert_eq!(
+            format!("{}", addr),
+            "Physical Address: 12 Long Street, Suite 5, Unit 4 - City 1 - Eastern Cape - 1234 - South Africa"
+        );
+        assert_eq!(
+            addr.to_value()["addressLineDetail"]["line3"],
+            json!("Unit 4")
+        );
     }
 
     #[test]
@@ -194,6 +1529,41 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_is_valid_matches_validate() {
+        for overrides in [
+            json!({ "country": { "code": "XX", "name": "Foo" } }),
+            json!({ "currency": "USD" }),
+            json!({}),
+        ] {
+            let addr = Address::with_overrides(overrides);
+            assert_eq!(addr.is_valid(), addr.validate().is_empty());
+        }
+        assert!(
+            !Address::with_overrides(json!({ "country": { "code": "XX", "name": "Foo" } }))
+                .is_valid()
+        );
+    }
+
+    #[test]
+    fn test_has_known_country_code() {
+        let known = |code: &str| {
+            Address::with_overrides(json!({ "country": { "code": code, "name": "Country" } }))
+                .country
+                .has_known_country_code()
+        };
+
+        assert!(known("ZA"));
+        assert!(known("US"));
+        assert!(known(""));
+        assert!(!known("XX"));
+
+        let addr = Address::with_overrides(json!({ "country": { "code": "XX", "name": "Foo" } }));
+        assert_eq!(addr.validate(), vec![ValidationError::UnknownCountryCode]);
+        let addr = Address::with_overrides(json!({ "country": { "name": "South Africa" } }));
+        assert!(addr.validate().is_empty());
+    }
+
     #[test]
     fn test_display_for_address() {
         Addresses::with_addresses(|addrs| {
@@ -212,37 +1582,650 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_accessors() {
+        Addresses::with_addresses(|addrs| {
+            let expected = [
+                ("City 1", "Eastern Cape", "1234", "ZA", "South Africa"),
+                ("City 2", "", "2345", "LB", "Lebanon"),
+                ("City 3", "", "3456", "ZA", "South Africa"),
+            ];
+            for (addr, (city, province, postal, code, country)) in
+                addrs.addresses.iter().zip(expected)
+            {
+                assert_eq!(addr.city_or_town(), city);
+                assert_eq!(addr.province_name(), province);
+                assert_eq!(addr.postal_code(), postal);
+                assert_eq!(addr.country_code(), code);
+                assert_eq!(addr.country_name(), country);
+                assert!(format!("{}", addr).ends_with(&format!(
+                    "- {} - {} - {} - {}",
+                    city,
+                    Address::str_or(province, NOT_AVAILABLE),
+                    postal,
+                    country
+                )));
+            }
+            assert_eq!(addrs.addresses[2].suburb_or_district(), "Suburb 3");
+            assert_eq!(
+                addrs.addresses[0].last_updated(),
+                "2015-06-21T00:00:00.000Z"
+            );
+        })
+    }
+
     #[test]
     fn test_validate() {
         Addresses::with_addresses(|addrs| {
             assert_eq!(addrs.addresses[0].validate(), Vec::<ValidationError>::new());
             assert_eq!(
                 addrs.addresses[1].validate(),
-                vec!["You must include valid address details (line 1 and/or 2 must be filled in)"]
+                vec![ValidationError::InvalidLineDetail]
             );
             assert_eq!(
                 addrs.addresses[2].validate(),
-                vec!["You must include a province if your country is ZA"]
+                vec![ValidationError::MissingProvince]
             );
         })
     }
 
+    #[test]
+    fn test_warnings() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.addresses[0].warnings(), vec![]);
+        });
+
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "12 Main Road, Cape Town", "line2": "" },
+            "cityOrTown": "Cape Town"
+        }));
+        assert_eq!(addr.warnings(), vec![ValidationWarning::CityInStreetLine]);
+        assert!(addr.validate().is_empty());
+
+        let addr = Address::with_overrides(json!({
+            "cityOrTown": "Cape Town",
+            "suburbOrDistrict": "CAPE TOWN"
+        }));
+        assert_eq!(addr.warnings(), vec![ValidationWarning::SuburbEqualsCity]);
+
+        let addr = Address::with_overrides(json!({
+            "country": { "code": "ZA", "name": "south africa" }
+        }));
+        assert_eq!(
+            addr.warnings(),
+            vec![ValidationWarning::CountryNameNotTitleCase]
+        );
+    }
+
+    #[test]
+    fn test_has_unexpected_unicode() {
+        Addresses::with_addresses(|addrs| {
+            assert!(!addrs.addresses[0].has_unexpected_unicode());
+        });
+
+        let addr = Address::with_overrides(json!({ "cityOrTown": "Cape Town 🌊" }));
+        assert_eq!(addr.warnings(), vec![ValidationWarning::UnexpectedUnicode]);
+        let rtl = Address::with_overrides(json!({ "cityOrTown": "Cape Town\u{200F}" }));
+        assert!(rtl.has_unexpected_unicode());
+        let accented = Address::with_overrides(json!({ "cityOrTown": "Saint-Étienne d’Arc" }));
+        assert!(!accented.has_unexpected_unicode());
+    }
+
+    #[test]
+    fn test_has_possible_mojibake() {
+        Addresses::with_addresses(|addrs| {
+            assert!(!addrs.addresses[0].has_possible_mojibake());
+        });
+
+        let addr = Address::with_overrides(json!({ "cityOrTown": "Saint-Ã‰tienne" }));
+        assert!(addr
+            .warnings()
+            .contains(&ValidationWarning::PossibleMojibake));
+        let addr = Address::with_overrides(json!({ "cityOrTown": "MontrÃ©al" }));
+        assert!(addr.has_possible_mojibake());
+        let clean = Address::with_overrides(json!({ "cityOrTown": "Montréal" }));
+        assert!(!clean.has_possible_mojibake());
+        assert!(clean.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_title_case() {
+        assert_eq!(Address::title_case("south AFRICA"), "South Africa");
+        assert_eq!(Address::title_case("South Africa"), "South Africa");
+        assert_eq!(Address::title_case(""), "");
+    }
+
+    #[test]
+    fn test_to_value() {
+        Addresses::with_addresses(|addrs| {
+            let value = addrs.addresses[0].to_value();
+            assert_eq!(value["id"], "1");
+            assert_eq!(value["type"]["name"], "Physical Address");
+            assert_eq!(value["addressLineDetail"]["line1"], "Address 1");
+            assert_eq!(value["provinceOrState"]["name"], "Eastern Cape");
+            assert_eq!(value["country"]["code"], "ZA");
+            assert_eq!(value["postalCode"], "1234");
+            assert!(value.get("suburbOrDistrict").is_none());
+
+            let json = addrs.addresses[0]
+                .to_json()
+                .expect("error serializing address");
+            assert_eq!(
+                value,
+                serde_json::from_str::<serde_json::Value>(&json).expect("error parsing json")
+            );
+        })
+    }
+
+    #[test]
+    fn test_canonicalize_country_code() {
+        let mut addr = Address::with_overrides(json!({
+            "country": { "code": "ZAF", "name": "South Africa" }
+        }));
+        assert!(addr.canonicalize_country_code());
+        assert_eq!(addr.country.code, "ZA");
+        assert!(!addr.canonicalize_country_code());
+
+        let mut addr = Address::with_overrides(json!({ "country": { "code": "lebanon" } }));
+        assert!(addr.canonicalize_country_code());
+        assert_eq!(addr.country.code, "LB");
+
+        let mut addr = Address::with_overrides(json!({ "country": { "name": "United States" } }));
+        assert!(addr.canonicalize_country_code());
+        assert_eq!(addr.country.code, "US");
+
+        let mut addr = Address::with_overrides(json!({ "country": { "code": "XYZ" } }));
+        assert!(!addr.canonicalize_country_code());
+        assert_eq!(addr.country.code, "XYZ");
+    }
+
+    #[test]
+    fn test_pad_postal_code() {
+        let mut addr = Address::with_overrides(json!({ "postalCode": "12" }));
+        addr.pad_postal_code(4);
+        assert_eq!(addr.postal_code, "0012");
+
+        let mut addr = Address::with_overrides(json!({ "postalCode": "123456" }));
+        addr.pad_postal_code(4);
+        assert_eq!(addr.postal_code, "123456");
+
+        let mut addr = Address::with_overrides(json!({ "postalCode": "A1" }));
+        addr.pad_postal_code(4);
+        assert_eq!(addr.postal_code, "A1");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "  12   Long Street ", "line2": "" },
+            "cityOrTown": "Cape  Town ",
+            "provinceOrState": { "code": " wc ", "name": "Western\tCape" },
+            "country": { "code": "za", "name": " South Africa" },
+            "postalCode": " 1234 ",
+        }));
+
+        addr.normalize();
+        assert_eq!(
+            format!("{}", addr),
+            "Physical Address: 12 Long Street - Cape Town - Western Cape - 1234 - South Africa"
+        );
+        assert_eq!(addr.province_or_state.code, "WC");
+        assert_eq!(addr.country.code, "ZA");
+        assert!(addr.validate().is_empty());
+
+        let mut addr = Address::with_overrides(json!({ "postalCode": " 1234 " }));
+        assert_eq!(addr.validate(), vec![ValidationError::InvalidPostalCode]);
+        addr.normalize();
+        assert!(addr.validate().is_empty());
+
+        let mut addr = Address::with_overrides(json!({ "postalCode": " 1  2 3 4" }));
+        addr.normalize();
+        assert_eq!(addr.postal_code, "1 2 3 4");
+    }
+
+    #[test]
+    fn test_normalize_line_order() {
+        let mut addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "Unit 4, Sea Point Court", "line2": "12 Main Road" },
+        }));
+        addr.normalize_line_order();
+        assert_eq!(addr.line_detail.line1, "12 Main Road");
+        assert_eq!(addr.line_detail.line2, "Unit 4, Sea Point Court");
+
+        addr.normalize_line_order();
+        assert_eq!(addr.line_detail.line1, "12 Main Road");
+
+        let mut addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "", "line2": "12 Main Road" },
+        }));
+        addr.normalize_line_order();
+        assert_eq!(addr.line_detail.line1, "12 Main Road");
+        assert_eq!(addr.line_detail.line2, "");
+
+        let mut addr = Address::with_overrides(json!({}));
+        addr.normalize_line_order();
+        assert_eq!(format!("{}", addr.line_detail), "Address 1, Line 2");
+    }
+
+    #[test]
+    fn test_field() {
+        Addresses::with_addresses(|addrs| {
+            let addr = &addrs.addresses[0];
+            assert_eq!(addr.field("id"), Some("1"));
+            assert_eq!(addr.field("line2"), Some("Line 2"));
+            assert_eq!(addr.field("line3"), Some(""));
+            assert_eq!(addr.field("city"), Some("City 1"));
+            assert_eq!(addr.field("country_code"), Some("ZA"));
+            assert_eq!(addr.field("unknown"), None);
+            assert!(FIELD_NAMES.iter().all(|name| addr.field(name).is_some()));
+        })
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let addr = Address::with_overrides(json!({ "id": "1" }));
+        let same = Address::with_overrides(json!({ "id": "2" }));
+        let other = Address::with_overrides(json!({ "postalCode": "4321" }));
+        assert_eq!(addr.canonical_string(), same.canonical_string());
+        assert!(!addr.canonical_string().contains("\"id\""));
+        assert!(!addr.canonical_string().contains("lastUpdated"));
+        assert_eq!(addr.fingerprint(), same.fingerprint());
+        assert_ne!(addr.fingerprint(), other.fingerprint());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_deterministic_id() {
+        let addr = Address::with_overrides(json!({ "id": "1" }));
+        let same = Address::with_overrides(json!({ "id": "2" }));
+        let other = Address::with_overrides(json!({ "postalCode": "4321" }));
+        assert_eq!(addr.deterministic_id(), same.deterministic_id());
+        assert_eq!(addr.deterministic_id(), addr.deterministic_id());
+        assert_ne!(addr.deterministic_id(), other.deterministic_id());
+        assert_eq!(addr.deterministic_id().len(), 36);
+
+        let updated = Address::with_overrides(json!({
+            "lastUpdated": "2020-01-01T00:00:00.000Z",
+            "currency": "ZAR",
+            "suburbOrDistrict": "Suburb 1",
+            "latitude": -33.92,
+            "longitude": 18.42
+        }));
+        assert_eq!(addr, updated);
+        assert_eq!(addr.deterministic_id(), updated.deterministic_id());
+    }
+
+    #[test]
+    fn test_to_redacted_value() {
+        Addresses::with_addresses(|addrs| {
+            let value = addrs.addresses[0].to_redacted_value("token");
+            assert_eq!(value["id"], "token");
+            assert_eq!(value["addressLineDetail"]["line1"], REDACTED);
+            assert_eq!(value["addressLineDetail"]["line2"], REDACTED);
+            assert_eq!(value["cityOrTown"], "City 1");
+            assert_eq!(
+                addrs.addresses[1].to_redacted_value("token")["addressLineDetail"]["line1"],
+                ""
+            );
+        });
+
+        let addr = Address::with_overrides(json!({ "latitude": -33.92, "longitude": 18.42 }));
+        assert!(addr.to_value().get("latitude").is_some());
+        let value = addr.to_redacted_value("token");
+        assert!(value.get("latitude").is_none());
+        assert!(value.get("longitude").is_none());
+        assert_eq!(value["postalCode"], "1234");
+    }
+
+    #[test]
+    fn test_conflicting_postal_country() {
+        Addresses::with_addresses(|addrs| {
+            addrs
+                .addresses
+                .iter()
+                .for_each(|addr| assert_eq!(addr.conflicting_postal_country(), None));
+        });
+
+        let canadian = Address::with_overrides(json!({ "postalCode": "K1A 0B1" }));
+        assert_eq!(canadian.conflicting_postal_country(), Some("CA"));
+        let canadian = Address::with_overrides(json!({
+            "country": { "code": "za", "name": "South Africa" },
+            "postalCode": "K1A 0B1"
+        }));
+        assert_eq!(canadian.conflicting_postal_country(), Some("CA"));
+        let unknown = Address::with_overrides(json!({ "postalCode": "ABC" }));
+        assert_eq!(unknown.conflicting_postal_country(), None);
+    }
+
+    #[test]
+    fn test_similarity() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.addresses[0].similarity(&addrs.addresses[0]), 1.0);
+            assert!(addrs.addresses[0].similarity(&addrs.addresses[1]) < 0.2);
+        });
+
+        let addr = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "12 Main Road", "line2": "Unit 4" },
+            "cityOrTown": "Cape Town"
+        }));
+        let near = Address::with_overrides(json!({
+            "id": "2",
+            "addressLineDetail": { "line1": "12 main road,", "line2": "" },
+            "cityOrTown": "CAPE TOWN"
+        }));
+        assert!(addr.similarity(&near) > 0.9);
+
+        let with_line3 = Address::with_overrides(json!({
+            "addressLineDetail": { "line1": "12 Main Road", "line2": "", "line3": "Unit 4" },
+            "cityOrTown": "Cape Town"
+        }));
+        assert_eq!(addr.similarity(&with_line3), 1.0);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let older = Address::with_overrides(json!({
+            "provinceOrState": {},
+            "cityOrTown": "Old City",
+            "suburbOrDistrict": "Suburb 1",
+            "lastUpdated": "2015-06-21T00:00:00.000Z"
+        }));
+        let newer = Address::with_overrides(json!({
+            "addressLineDetail": {},
+            "cityOrTown": "New City",
+            "lastUpdated": "2016-06-21T00:00:00.000Z"
+        }));
+        let merged = older.coalesce(newer);
+        assert_eq!(
+            format!("{}", merged),
+            "Physical Address: Address 1, Line 2 - New City - Eastern Cape - 1234 - South Africa"
+        );
+        assert_eq!(merged.suburb_or_district, "Suburb 1");
+        assert_eq!(merged.last_updated, "2016-06-21T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_word_overlap() {
+        assert_eq!(Address::word_overlap("Main Road", "main  road"), 1.0);
+        assert_eq!(Address::word_overlap("Main Road", "Main Street"), 1.0 / 3.0);
+        assert_eq!(Address::word_overlap("", ""), 1.0);
+        assert_eq!(Address::word_overlap("Main", ""), 0.0);
+    }
+
+    #[test]
+    fn test_has_province_without_country() {
+        Addresses::with_addresses(|addrs| {
+            assert!(!addrs.addresses[0].has_province_without_country());
+            assert!(!addrs.addresses[1].has_province_without_country());
+        });
+
+        let addr = Address::with_overrides(json!({ "country": {} }));
+        assert!(addr.has_province_without_country());
+    }
+
+    #[test]
+    fn test_display_for_validation_error() {
+        assert_eq!(
+            format!("{}", ValidationError::MissingProvince),
+            "You must include a province if your country is ZA"
+        );
+        assert_eq!(
+            format!("{}", ValidationError::InvalidPostalCode),
+            "You must include a valid postal code"
+        );
+        let err: Box<dyn std::error::Error> = Box::new(ValidationError::MissingCountry);
+        assert_eq!(err.to_string(), "You must include a country");
+    }
+
     #[test]
     fn test_is_valid() {
         Addresses::with_addresses(|addrs| {
             assert!(addrs.addresses[0].is_valid());
             assert!(!addrs.addresses[1].is_valid());
             assert!(!addrs.addresses[2].is_valid());
+        });
+
+        let addr = Address::with_overrides(json!({ "currency": "USD" }));
+        assert!(!addr.is_valid());
+    }
+
+    #[test]
+    fn test_completeness_score() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.addresses[0].completeness_score(), 85);
+            assert_eq!(addrs.addresses[1].completeness_score(), 57);
+            assert_eq!(addrs.addresses[2].completeness_score(), 85);
+        });
+
+        let addr = Address::with_overrides(json!({ "suburbOrDistrict": "Suburb 1" }));
+        assert_eq!(addr.completeness_score(), 100);
+    }
+
+    #[test]
+    fn test_validate_min_completeness() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.addresses[0].validate_min_completeness(80),
+                Vec::<ValidationError>::new()
+            );
+            assert_eq!(
+                addrs.addresses[1].validate_min_completeness(80),
+                vec![
+                    ValidationError::InvalidLineDetail,
+                    ValidationError::InsufficientCompleteness
+                ]
+            );
+            assert_eq!(
+                addrs.addresses[0].validate_min_completeness(90),
+                vec![ValidationError::InsufficientCompleteness]
+            );
         })
     }
 
+    #[test]
+    fn test_validate_field_group_consistency() {
+        let group = ["city", "postal_code", "province"];
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.addresses[0].validate_field_group_consistency(&group),
+                Ok(vec![])
+            );
+            assert_eq!(
+                addrs.addresses[1].validate_field_group_consistency(&group),
+                Ok(vec![ValidationError::PartialFieldGroup])
+            );
+            assert_eq!(
+                addrs.addresses[0].validate_field_group_consistency(&["city", "provnce"]),
+                Err("unknown field: provnce".to_string())
+            );
+        });
+
+        let empty = Address::with_overrides(json!({
+            "provinceOrState": {},
+            "cityOrTown": "",
+            "postalCode": ""
+        }));
+        assert_eq!(empty.validate_field_group_consistency(&group), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_validate_db_limits() {
+        let limits = HashMap::from([("city", 10), ("postal_code", 4), ("unknown", 1)]);
+        Addresses::with_addresses(|addrs| {
+            assert!(addrs.addresses[0].validate_db_limits(&limits).is_empty());
+        });
+
+        let addr = Address::with_overrides(json!({ "cityOrTown": "Port Elizabeth" }));
+        assert_eq!(
+            addr.validate_db_limits(&limits),
+            vec![ValidationError::FieldExceedsDbLimit("city")]
+        );
+        assert_eq!(
+            ValidationError::FieldExceedsDbLimit("city").to_string(),
+            "The city field is longer than its database column allows"
+        );
+    }
+
+    #[test]
+    fn test_validate_with_field_regex() {
+        let rules = HashMap::from([
+            ("city", Regex::new(r"^[A-Z][a-z]+( [A-Z][a-z]+)*$").unwrap()),
+            ("suburb", Regex::new(r"^\d+$").unwrap()),
+        ]);
+        let addr = Address::with_overrides(json!({ "cityOrTown": "Cape Town" }));
+        assert!(addr.validate_with_field_regex(&rules).is_empty());
+
+        let addr = Address::with_overrides(json!({ "cityOrTown": "cape town 1" }));
+        assert_eq!(
+            addr.validate_with_field_regex(&rules),
+            vec![ValidationError::FieldRegexMismatch("city")]
+        );
+        assert_eq!(
+            ValidationError::FieldRegexMismatch("city").to_string(),
+            "The city field does not match its configured format"
+        );
+    }
+
+    #[test]
+    fn test_validate_type_field_requirements() {
+        Addresses::with_addresses(|addrs| {
+            addrs.addresses.iter().for_each(|addr| {
+                assert!(addr.validate_type_field_requirements().is_empty());
+            })
+        });
+
+        let addr = Address::with_overrides(json!({
+            "type": { "code": "2", "name": "Postal Address" },
+            "postalCode": "",
+        }));
+        assert_eq!(
+            addr.validate_type_field_requirements(),
+            vec![ValidationError::TypeRequirementUnmet("a postal code")]
+        );
+        assert_eq!(
+            ValidationError::TypeRequirementUnmet("a postal code").to_string(),
+            "The address type requires a postal code"
+        );
+
+        let addr = Address::with_overrides(json!({ "addressLineDetail": {} }));
+        assert_eq!(
+            addr.validate_type_field_requirements(),
+            vec![ValidationError::TypeRequirementUnmet(
+                "address line details"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_province_in_country() {
+        Addresses::with_addresses(|addrs| {
+            addrs.addresses.iter().for_each(|addr| {
+                assert!(addr.validate_province_in_country().is_empty());
+            })
+        });
+
+        let addr = Address::with_overrides(json!({
+            "country": { "code": "LB", "name": "Lebanon" },
+        }));
+        assert_eq!(
+            addr.validate_province_in_country(),
+            vec![ValidationError::ProvinceNotInCountry]
+        );
+
+        let addr = Address::with_overrides(json!({
+            "provinceOrState": { "name": "western cape" },
+        }));
+        assert!(addr.validate_province_in_country().is_empty());
+
+        let addr = Address::with_overrides(json!({
+            "country": { "code": "FR", "name": "France" },
+        }));
+        assert!(addr.validate_province_in_country().is_empty());
+    }
+
+    #[test]
+    fn test_validate_freshness() {
+        let now = Utc.with_ymd_and_hms(2020, 1, 31, 0, 0, 0).unwrap();
+        let addr = Address::with_overrides(json!({}));
+        assert_eq!(
+            addr.validate_freshness(now, 365),
+            vec![ValidationError::StaleRecord]
+        );
+
+        let addr = Address::with_overrides(json!({ "lastUpdated": "2020-01-01T00:00:00Z" }));
+        assert!(addr.validate_freshness(now, 30).is_empty());
+        assert_eq!(
+            addr.validate_freshness(now, 29),
+            vec![ValidationError::StaleRecord]
+        );
+
+        assert!(addr.validate_freshness(now, i64::MAX).is_empty());
+        assert!(addr.validate_freshness(now, i64::MIN).is_empty());
+
+        let addr = Address::with_overrides(json!({ "lastUpdated": "yesterday" }));
+        assert!(addr.validate_freshness(now, 0).is_empty());
+    }
+
+    #[test]
+    fn test_has_valid_currency() {
+        Addresses::with_addresses(|addrs| {
+            assert!(addrs.addresses[0].has_valid_currency());
+        });
+
+        let addr = Address::with_overrides(json!({ "currency": "USD" }));
+        assert!(!addr.has_valid_currency());
+        assert_eq!(
+            addr.validate(),
+            vec![ValidationError::CurrencyCountryMismatch]
+        );
+        assert!(Address::with_overrides(json!({ "currency": "zar" })).has_valid_currency());
+        let lowercase = Address::with_overrides(json!({
+            "country": { "code": "za", "name": "South Africa" },
+            "currency": "USD"
+        }));
+        assert!(!lowercase.has_valid_currency());
+        let unknown = Address::with_overrides(json!({
+            "country": { "code": "XX", "name": "Nowhere" },
+            "currency": "USD"
+        }));
+        assert!(unknown.has_valid_currency());
+    }
+
     #[test]
     fn test_has_valid_province() {
         Addresses::with_addresses(|addrs| {
             assert!(addrs.addresses[0].has_valid_province());
             assert!(addrs.addresses[1].has_valid_province());
             assert!(!addrs.addresses[2].has_valid_province());
-        })
+        });
+
+        for code in ["za", "Za", "ZA"] {
+            let addr = Address::with_overrides(json!({
+                "country": { "code": code, "name": "South Africa" },
+                "provinceOrState": {},
+            }));
+            assert!(!addr.has_valid_province());
+        }
+    }
+
+    #[test]
+    fn test_has_valid_province_with() {
+        let required = HashSet::from(["ZA", "US", "CA", "AU"]);
+        let us = |state: &str| {
+            Address::with_overrides(json!({
+                "country": { "code": "US", "name": "United States" },
+                "provinceOrState": { "name": state },
+            }))
+        };
+
+        assert!(us("California").has_valid_province_with(&required));
+        assert!(!us("").has_valid_province_with(&required));
+        assert!(us("").has_valid_province());
+
+        let za = Address::with_overrides(json!({ "provinceOrState": {} }));
+        assert!(!za.has_valid_province_with(&required));
+        assert!(!za.has_valid_province());
+        assert!(za.has_valid_province_with(&HashSet::new()));
     }
 
     #[test]
@@ -253,8 +2236,44 @@ mod tests {
 
     #[test]
     fn test_is_valid_postal_code() {
-        assert!(Address::is_valid_postal_code("1234"));
-        assert!(!Address::is_valid_postal_code("abcd"));
-        assert!(!Address::is_valid_postal_code("a2c4"));
+        let valid = |code: &str, postal_code: &str| {
+            Address::with_overrides(json!({
+                "country": { "code": code, "name": code },
+                "postalCode": postal_code
+            }))
+            .is_valid_postal_code()
+        };
+
+        assert!(valid("ZA", "1234"));
+        assert!(!valid("ZA", "12345"));
+        assert!(!valid("ZA", "a2c4"));
+        assert!(!valid("za", "12345"));
+        assert!(valid("US", "90210"));
+        assert!(valid("US", "90210-1234"));
+        assert!(!valid("US", "9021"));
+        assert!(valid("CA", "K1A 0B1"));
+        assert!(!valid("CA", "K1A 0B"));
+        assert!(valid("GB", "SW1A 1AA"));
+        assert!(valid("GB", "M1 1AE"));
+        assert!(!valid("GB", "SW1A"));
+        assert!(valid("NL", "1234 AB"));
+        assert!(valid("LB", "2345"));
+        assert!(valid("LB", "00000000000"));
+        assert!(!valid("LB", ""));
+    }
+
+    #[test]
+    fn test_numeric_postal_code() {
+        let postal = |code: &str| {
+            Address::with_overrides(json!({ "postalCode": code })).numeric_postal_code()
+        };
+        assert_eq!(postal("1234"), Some(1234));
+        assert_eq!(postal("0012"), Some(12));
+        assert_eq!(postal("abcd"), None);
+        assert_eq!(postal("a2c4"), None);
+        assert_eq!(postal("-123"), None);
+        assert_eq!(postal("+123"), None);
+        assert_eq!(postal(""), None);
+        assert_eq!(postal("99999999999"), None);
     }
 }
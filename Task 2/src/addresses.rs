@@ -1,26 +1,241 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 
-use crate::address;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "gzip")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "icu")]
+use icu_collator::{Collator, CollatorOptions};
+#[cfg(feature = "icu")]
+use icu_locid::Locale;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 
+use crate::address::{self, ValidationError, FIELD_NAMES, NOT_AVAILABLE};
+use crate::city_index::CityIndex;
+
+#[cfg(test)]
 const JSON_FILE_PATH: &str = "src/addresses.json";
+const COALESCE_SIMILARITY: f64 = 0.5;
+const UNKNOWN_COUNTRY: &str = "(unknown country)";
+const UNKNOWN_PROVINCE: &str = "(unknown province)";
 
 #[derive(Debug)]
 pub struct Addresses {
-    pub addresses: Vec<address::Address>,
+    pub(crate) addresses: Vec<address::Address>,
+}
+
+impl From<Vec<address::Address>> for Addresses {
+    fn from(addresses: Vec<address::Address>) -> Self {
+        Self { addresses }
+    }
+}
+
+impl IntoIterator for Addresses {
+    type Item = address::Address;
+    type IntoIter = std::vec::IntoIter<address::Address>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.addresses.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Addresses {
+    type Item = &'a address::Address;
+    type IntoIter = std::slice::Iter<'a, address::Address>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.addresses.iter()
+    }
+}
+
+impl std::ops::Index<usize> for Addresses {
+    type Output = address::Address;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.addresses[index]
+    }
+}
+
+/// The address fields that Addresses::sort_by can sort on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressSortKey {
+    PostalCode,
+    City,
+    Country,
+    LastUpdated,
+}
+
+/// The criteria that Addresses::query combines with AND semantics. Unset criteria match every
+/// address.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddressQuery {
+    /// The country code, ignoring case.
+    pub country: Option<String>,
+    /// The address type name, ignoring case.
+    pub type_name: Option<String>,
+    /// The inclusive range of numeric postal codes. Non-numeric postal codes don't match.
+    pub postal_range: Option<(i32, i32)>,
+    /// Only matches addresses without validation errors.
+    pub valid_only: bool,
+}
+
+/// Calculates the GCD of two i32 integer values, mirroring the solution to assignment one.
+fn calculate_gcd(mut a: i32, mut b: i32) -> i32 {
+    if a < b {
+        (a, b) = (b, a);
+    }
+
+    while b != 0 {
+        (b, a) = (a % b, b);
+    }
+
+    a
+}
+
+/// The number of valid and invalid addresses in a collection, and how often each validation error
+/// occurred.
+#[derive(Debug, Default, PartialEq)]
+pub struct ValidationSummary {
+    pub total: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    pub error_counts: HashMap<ValidationError, usize>,
+}
+
+/// The ids of the addresses that were added, removed or modified between two collections.
+#[derive(Debug, Default, PartialEq)]
+pub struct CollectionDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+#[allow(dead_code)]
+/// Compares two collections by address id. An address is modified if its location fingerprint
+/// (see Address::fingerprint) differs between the old and new collections.
+pub fn diff_collections(old: &Addresses, new: &Addresses) -> CollectionDiff {
+    let old_fingerprints: HashMap<&str, u64> = old
+        .addresses
+        .iter()
+        .map(|addr| (addr.id.as_str(), addr.fingerprint()))
+        .collect();
+    let new_ids: HashSet<&str> = new.addresses.iter().map(|addr| addr.id.as_str()).collect();
+
+    let mut diff = CollectionDiff::default();
+    new.addresses
+        .iter()
+        .for_each(|addr| match old_fingerprints.get(addr.id.as_str()) {
+            None => diff.added.push(addr.id.clone()),
+            Some(fingerprint) if *fingerprint != addr.fingerprint() => {
+                diff.modified.push(addr.id.clone())
+            }
+            _ => {}
+        });
+    old.addresses
+        .iter()
+        .filter(|addr| !new_ids.contains(addr.id.as_str()))
+        .for_each(|addr| diff.removed.push(addr.id.clone()));
+    diff
+}
+
+#[allow(dead_code)]
+/// Returns the GCD of the numeric postal codes across all the given collections, or None if no
+/// postal code is numeric.
+pub fn postal_gcd_across(collections: &[&Addresses]) -> Option<i32> {
+    collections
+        .iter()
+        .flat_map(|addrs| addrs.addresses.iter())
+        .filter_map(address::Address::numeric_postal_code)
+        .reduce(calculate_gcd)
 }
 
 #[allow(dead_code)]
 impl Addresses {
+    /// Returns an iterator over the addresses in order.
+    pub fn iter(&self) -> impl Iterator<Item = &address::Address> {
+        self.addresses.iter()
+    }
+
+    /// Returns the number of addresses.
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Returns true if there are no addresses.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// A factory method to create an Addresses instance from a json string.
+    pub fn from_json_str(data: &str) -> Result<Self, String> {
+        Ok(Self {
+            addresses: serde_json::from_str(data)
+                .map_err(|err| format!("error deserializing json string: {:?}", err))?,
+        })
+    }
+
+    /// A factory method to create an Addresses instance from a reader of json, e.g. a socket or a
+    /// decompressing stream. The json is deserialized as it is read.
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, String> {
+        Ok(Self {
+            addresses: serde_json::from_reader(reader)
+                .map_err(|err| format!("error deserializing json string: {:?}", err))?,
+        })
+    }
+
     /// A factory method to create an Addresses instance from a json file.
     pub fn from_json_file(path: &str) -> Result<Self, String> {
-        let data = fs::read_to_string(path)
-            .map_err(|err| format!("error importing json file: {:?}", err))?;
+        let file =
+            fs::File::open(path).map_err(|err| format!("error importing json file: {:?}", err))?;
+        Self::from_reader(std::io::BufReader::new(file))
+    }
+
+    /// A factory method to create an Addresses instance from a gzip-compressed json file.
+    #[cfg(feature = "gzip")]
+    pub fn from_json_gz_file(path: &str) -> Result<Self, String> {
+        let file =
+            fs::File::open(path).map_err(|err| format!("error importing json file: {:?}", err))?;
+        Self::from_reader(GzDecoder::new(file))
+    }
+
+    /// A factory method to create an Addresses instance from a json file, after checking that the
+    /// SHA-256 checksum of the file matches the expected hex encoded checksum.
+    pub fn from_json_file_verified(path: &str, expected_sha256: &str) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|err| format!("error importing json file: {:?}", err))?;
+        let checksum: String = Sha256::digest(&data)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        if !checksum.eq_ignore_ascii_case(expected_sha256) {
+            return Err(format!(
+                "checksum mismatch: expected {} but found {}",
+                expected_sha256, checksum
+            ));
+        }
+
         Ok(Self {
-            addresses: serde_json::from_str(&data)
+            addresses: serde_json::from_slice(&data)
                 .map_err(|err| format!("error deserializing json string: {:?}", err))?,
         })
     }
 
+    /// Returns a pretty printed json array of the addresses in the input schema.
+    pub fn to_json_string(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(&self.addresses)
+            .map_err(|err| format!("error serializing json string: {:?}", err))
+    }
+
+    /// Writes a pretty printed json array of the addresses in the input schema to a file.
+    pub fn to_json_file(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_json_string()?)
+            .map_err(|err| format!("error writing json file: {:?}", err))
+    }
+
     /// The solution to b.
     pub fn pretty_print_addresses(&self) {
         self.addresses.iter().for_each(|addr| println!("{addr}"));
@@ -28,21 +243,848 @@ impl Addresses {
 
     /// The solution to e.
     pub fn validate_addresses(&self) -> Vec<String> {
-        let mut err_strings = Vec::new();
+        self.addresses
+            .iter()
+            .filter_map(Self::validation_message)
+            .collect()
+    }
+
+    /// Validates the addresses across threads, returning the same messages in the same order as
+    /// validate_addresses.
+    #[cfg(feature = "rayon")]
+    pub fn validate_addresses_parallel(&self) -> Vec<String> {
+        self.addresses
+            .par_iter()
+            .filter_map(Self::validation_message)
+            .collect()
+    }
+
+    /// Writes a line for each invalid address to the writer as the addresses are validated, so that
+    /// the messages don't have to be buffered.
+    pub fn stream_validation<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        for msg in self.addresses.iter().filter_map(Self::validation_message) {
+            writeln!(w, "{msg}")?;
+        }
+        Ok(())
+    }
+
+    /// Returns a message with the validation errors of the address, or None if it is valid.
+    fn validation_message(addr: &address::Address) -> Option<String> {
+        let errs = addr.validate();
+        if errs.is_empty() {
+            return None;
+        }
+
+        // Debug formatting the messages keeps the output identical to when errors were strings.
+        let errs: Vec<String> = errs.iter().map(ToString::to_string).collect();
+        Some(format!(
+            "Address for ID: {} is invalid. Validation errors: {:?}",
+            addr.id, errs
+        ))
+    }
+
+    /// Returns a json string of the addresses with the address lines masked and the ids replaced by
+    /// opaque tokens, so that the data can be shared externally.
+    pub fn to_redacted_json(&self) -> Result<String, String> {
+        let values: Vec<_> = self
+            .addresses
+            .iter()
+            .enumerate()
+            .map(|(i, addr)| addr.to_redacted_value(&format!("address-{}", i + 1)))
+            .collect();
+        serde_json::to_string_pretty(&values)
+            .map_err(|err| format!("error serializing json string: {:?}", err))
+    }
+
+    /// Counts the addresses per address type name.
+    pub fn count_by_type(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        self.addresses.iter().for_each(|addr| {
+            *counts.entry(addr.type_name().to_string()).or_default() += 1;
+        });
+        counts
+    }
+
+    /// Groups the addresses by address type name in input order, with addresses without a type name
+    /// under "Not available".
+    pub fn group_by_type(&self) -> HashMap<String, Vec<&address::Address>> {
+        let mut groups: HashMap<String, Vec<&address::Address>> = HashMap::new();
+        self.addresses.iter().for_each(|addr| {
+            let name = match addr.type_name() {
+                "" => NOT_AVAILABLE,
+                name => name,
+            };
+            groups.entry(name.to_string()).or_default().push(addr);
+        });
+        groups
+    }
+
+    /// Returns each address type name with its fraction of the total number of addresses, sorted
+    /// from the largest to the smallest fraction and then by name.
+    pub fn type_distribution(&self) -> Vec<(String, f64)> {
+        let total = self.addresses.len() as f64;
+        let mut distribution: Vec<(String, f64)> = self
+            .count_by_type()
+            .into_iter()
+            .map(|(name, count)| (name, count as f64 / total))
+            .collect();
+        distribution.sort_by(|(n1, f1), (n2, f2)| f2.total_cmp(f1).then_with(|| n1.cmp(n2)));
+        distribution
+    }
+
+    /// Returns an A to F data quality grade of the collection, based on the average of the
+    /// percentage of valid addresses and the average completeness score. The grade is 'A' for a
+    /// score of at least 95, 'B' for at least 85, 'C' for at least 75, 'D' for at least 65 and 'F'
+    /// otherwise. An empty collection is graded 'F'.
+    pub fn quality_grade(&self) -> char {
+        if self.addresses.is_empty() {
+            return 'F';
+        }
+
+        let total = self.addresses.len() as f64;
+        let valid = self.addresses.iter().filter(|addr| addr.is_valid()).count() as f64;
+        let completeness = self
+            .addresses
+            .iter()
+            .map(|addr| f64::from(addr.completeness_score()))
+            .sum::<f64>();
+        let score = (valid * 100.0 + completeness) / (2.0 * total);
+
+        match score {
+            s if s >= 95.0 => 'A',
+            s if s >= 85.0 => 'B',
+            s if s >= 75.0 => 'C',
+            s if s >= 65.0 => 'D',
+            _ => 'F',
+        }
+    }
+
+    /// Returns a message for each address whose postal code format implies a different country than
+    /// the one recorded.
+    pub fn flag_postal_country_conflicts(&self) -> Vec<String> {
+        self.addresses
+            .iter()
+            .filter_map(|addr| {
+                addr.conflicting_postal_country().map(|implied| {
+                    format!(
+                        "Address for ID: {} has a postal code ({}) that looks like {} but its country is {}",
+                        addr.id,
+                        addr.postal_code(),
+                        implied,
+                        addr.country_code()
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the addresses that were last updated after the given time. Addresses with an
+    /// unparseable timestamp are skipped.
+    pub fn future_dated(&self, now: DateTime<Utc>) -> Vec<&address::Address> {
+        self.addresses
+            .iter()
+            .filter(|addr| addr.last_updated_datetime().is_ok_and(|dt| dt > now))
+            .collect()
+    }
+
+    /// Returns the address with the latest last updated timestamp, or None if no address has a
+    /// parseable timestamp. Ties go to the address that comes first.
+    pub fn most_recently_updated(&self) -> Option<&address::Address> {
+        self.addresses
+            .iter()
+            .filter_map(|addr| addr.last_updated_datetime().ok().map(|dt| (dt, addr)))
+            .reduce(|latest, next| match next.0 > latest.0 {
+                true => next,
+                false => latest,
+            })
+            .map(|(_, addr)| addr)
+    }
+
+    /// Returns a random sample of n distinct addresses chosen with an RNG seeded by the given seed,
+    /// so the same seed always returns the same sample. n is clamped to the number of addresses.
+    pub fn sample(&self, n: usize, seed: u64) -> Vec<&address::Address> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.addresses
+            .choose_multiple(&mut rng, n.min(self.addresses.len()))
+            .collect()
+    }
+
+    /// Returns the n addresses most similar to the query address with their similarity scores, most
+    /// similar first.
+    pub fn most_similar_to(
+        &self,
+        query: &address::Address,
+        n: usize,
+    ) -> Vec<(&address::Address, f64)> {
+        let mut scored: Vec<(&address::Address, f64)> = self
+            .addresses
+            .iter()
+            .map(|addr| (addr, addr.similarity(query)))
+            .collect();
+        scored.sort_by(|(_, s1), (_, s2)| s2.total_cmp(s1));
+        scored.truncate(n);
+        scored
+    }
+
+    /// Removes the addresses whose similarity to an earlier kept address exceeds the threshold and
+    /// returns the number of addresses removed.
+    pub fn dedup_fuzzy(&mut self, threshold: f64) -> usize {
+        let before = self.addresses.len();
+        let mut kept: Vec<address::Address> = Vec::with_capacity(before);
+        for addr in self.addresses.drain(..) {
+            if !kept.iter().any(|k| k.similarity(&addr) > threshold) {
+                kept.push(addr);
+            }
+        }
+        self.addresses = kept;
+        before - self.addresses.len()
+    }
+
+    /// Appends the addresses of the other collection.
+    pub fn merge(&mut self, other: Addresses) {
+        self.addresses.extend(other.addresses);
+    }
+
+    /// Appends the addresses of the other collection that are not already present. An address is
+    /// skipped if it is at the same location as an existing address (see the PartialEq impl of
+    /// Address), or if its id collides with the id of an existing address, in which case the
+    /// existing address is kept. Addresses appended earlier in the merge count as existing.
+    pub fn merge_dedup(&mut self, other: Addresses) {
+        for addr in other.addresses {
+            if !self
+                .addresses
+                .iter()
+                .any(|existing| *existing == addr || existing.id == addr.id)
+            {
+                self.addresses.push(addr);
+            }
+        }
+    }
+
+    /// Removes the addresses at the same location as an earlier address (see the PartialEq impl of
+    /// Address), keeping the first occurrence.
+    pub fn dedup(&mut self) {
+        let mut kept: Vec<address::Address> = Vec::with_capacity(self.addresses.len());
+        for addr in self.addresses.drain(..) {
+            if !kept.contains(&addr) {
+                kept.push(addr);
+            }
+        }
+        self.addresses = kept;
+    }
+
+    /// Returns each address that dedup would remove, paired with the earlier address it duplicates.
+    pub fn duplicates(&self) -> Vec<(&address::Address, &address::Address)> {
+        self.addresses
+            .iter()
+            .enumerate()
+            .filter_map(|(i, addr)| {
+                self.addresses[..i]
+                    .iter()
+                    .find(|earlier| *earlier == addr)
+                    .map(|earlier| (earlier, addr))
+            })
+            .collect()
+    }
+
+    /// Returns a json string of the invalid addresses, each with its validation errors listed under
+    /// "validationErrors", for a correction queue.
+    pub fn invalid_to_json(&self) -> Result<String, String> {
+        let values: Vec<_> = self
+            .addresses
+            .iter()
+            .filter_map(|addr| {
+                let errs = addr.validate();
+                if errs.is_empty() {
+                    return None;
+                }
+                let mut value = addr.to_value();
+                let errs: Vec<String> = errs.iter().map(ToString::to_string).collect();
+                value["validationErrors"] = serde_json::json!(errs);
+                Some(value)
+            })
+            .collect();
+        serde_json::to_string_pretty(&values)
+            .map_err(|err| format!("error serializing json string: {:?}", err))
+    }
+
+    /// Writes the addresses of each country to a json file named after the country code in the
+    /// given directory, e.g. "{dir}/ZA.json", creating the directory if needed. Country codes are
+    /// uppercased, and addresses whose country code is not two letters are written to
+    /// "{dir}/unknown.json". Returns the written paths sorted by file name.
+    pub fn write_json_by_country(&self, dir: &str) -> Result<Vec<String>, String> {
+        let mut groups: BTreeMap<String, Vec<&address::Address>> = BTreeMap::new();
+        self.addresses.iter().for_each(|addr| {
+            let code = addr.country_code().to_uppercase();
+            let code = match code.len() == 2 && code.bytes().all(|b| b.is_ascii_uppercase()) {
+                true => code,
+                false => "unknown".to_string(),
+            };
+            groups.entry(code).or_default().push(addr);
+        });
+
+        fs::create_dir_all(dir).map_err(|err| format!("error creating directory: {:?}", err))?;
+        groups
+            .iter()
+            .map(|(code, group)| {
+                let path = format!("{}/{}.json", dir, code);
+                let data = serde_json::to_string_pretty(group)
+                    .map_err(|err| format!("error serializing json string: {:?}", err))?;
+                fs::write(&path, data)
+                    .map_err(|err| format!("error writing json file: {:?}", err))?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Returns the addresses with the given country code, ignoring case.
+    pub fn filter_by_country(&self, code: &str) -> Vec<&address::Address> {
+        self.addresses
+            .iter()
+            .filter(|addr| addr.country_code().eq_ignore_ascii_case(code))
+            .collect()
+    }
+
+    /// Groups the addresses by their uppercased country code, with addresses without a country code
+    /// under an empty code.
+    pub fn group_by_country(&self) -> HashMap<String, Vec<&address::Address>> {
+        let mut groups: HashMap<String, Vec<&address::Address>> = HashMap::new();
+        self.addresses.iter().for_each(|addr| {
+            groups
+                .entry(addr.country_code().to_uppercase())
+                .or_default()
+                .push(addr);
+        });
+        groups
+    }
+
+    /// Returns the addresses interleaved across countries, taking one address from each country in
+    /// turn. Country codes are compared ignoring case, and countries are visited in the order they
+    /// first appear.
+    pub fn round_robin_by_country(&self) -> Vec<&address::Address> {
+        let mut groups: Vec<(&str, Vec<&address::Address>)> = Vec::new();
+        self.addresses.iter().for_each(|addr| {
+            match groups
+                .iter_mut()
+                .find(|(code, _)| code.eq_ignore_ascii_case(addr.country_code()))
+            {
+                Some((_, group)) => group.push(addr),
+                None => groups.push((addr.country_code(), vec![addr])),
+            }
+        });
+
+        let rounds = groups
+            .iter()
+            .map(|(_, group)| group.len())
+            .max()
+            .unwrap_or(0);
+        (0..rounds)
+            .flat_map(|round| groups.iter().filter_map(move |(_, group)| group.get(round)))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the addresses sorted by city using the collation rules of the given locale, e.g. "en"
+    /// or "sv". Falls back to the root collation if the locale cannot be parsed. The sort is stable,
+    /// so addresses in the same city keep their original order.
+    #[cfg(feature = "icu")]
+    pub fn sorted_by_city_collated(&self, locale: &str) -> Vec<&address::Address> {
+        let locale = locale.parse::<Locale>().unwrap_or(Locale::UND);
+        let collator = Collator::try_new(&(&locale).into(), CollatorOptions::new())
+            .expect("error loading collation data");
+
+        let mut sorted: Vec<&address::Address> = self.addresses.iter().collect();
+        sorted.sort_by(|a, b| collator.compare(a.city_or_town(), b.city_or_town()));
+        sorted
+    }
+
+    /// Returns the GCD of the differences between consecutive sorted numeric postal codes, i.e. the
+    /// largest spacing of a grid that every postal code falls on. Returns None if there are fewer
+    /// than two distinct numeric postal codes.
+    pub fn postal_grid_spacing(&self) -> Option<i32> {
+        let mut codes: Vec<i32> = self
+            .addresses
+            .iter()
+            .filter_map(address::Address::numeric_postal_code)
+            .collect();
+        codes.sort_unstable();
+
+        // Numeric postal codes are never negative, so the differences of sorted codes fit an i32.
+        match codes.windows(2).map(|w| w[1] - w[0]).fold(0, calculate_gcd) {
+            0 => None,
+            spacing => Some(spacing),
+        }
+    }
+
+    /// Returns true if the addresses are in the order that sort_by(AddressSortKey::PostalCode)
+    /// leaves them in, i.e. numeric postal codes in non-decreasing numeric order followed by the
+    /// other postal codes in lexical order.
+    pub fn is_sorted_by_postal(&self) -> bool {
+        self.addresses
+            .windows(2)
+            .all(|w| Self::postal_sort_key(&w[0]) <= Self::postal_sort_key(&w[1]))
+    }
+
+    /// Returns the key that addresses are ordered by when sorting by postal code.
+    fn postal_sort_key(addr: &address::Address) -> (bool, Option<i32>, &str) {
+        let numeric = addr.numeric_postal_code();
+        (numeric.is_none(), numeric, addr.postal_code())
+    }
+
+    /// Sorts the addresses by the given key, keeping the order of addresses with equal keys.
+    /// Numeric postal codes sort numerically before the other postal codes, which sort lexically.
+    /// Countries sort by name, and addresses with an unparseable timestamp sort after the others.
+    pub fn sort_by(&mut self, key: AddressSortKey) {
+        match key {
+            AddressSortKey::PostalCode => self
+                .addresses
+                .sort_by(|a, b| Self::postal_sort_key(a).cmp(&Self::postal_sort_key(b))),
+            AddressSortKey::City => self
+                .addresses
+                .sort_by(|a, b| a.city_or_town().cmp(b.city_or_town())),
+            AddressSortKey::Country => self
+                .addresses
+                .sort_by(|a, b| a.country_name().cmp(b.country_name())),
+            AddressSortKey::LastUpdated => self.addresses.sort_by_key(|addr| {
+                let updated = addr.last_updated_datetime().ok();
+                (updated.is_none(), updated)
+            }),
+        }
+    }
+
+    /// Returns the addresses that match every set criterion of the query, in order.
+    pub fn query<'a>(&'a self, q: &AddressQuery) -> Vec<&'a address::Address> {
+        self.addresses
+            .iter()
+            .filter(|addr| {
+                q.country
+                    .as_ref()
+                    .is_none_or(|code| addr.country_code().eq_ignore_ascii_case(code))
+            })
+            .filter(|addr| {
+                q.type_name
+                    .as_ref()
+                    .is_none_or(|name| addr.type_name().eq_ignore_ascii_case(name))
+            })
+            .filter(|addr| {
+                q.postal_range.is_none_or(|(low, high)| {
+                    addr.numeric_postal_code()
+                        .is_some_and(|code| (low..=high).contains(&code))
+                })
+            })
+            .filter(|addr| !q.valid_only || addr.is_valid())
+            .collect()
+    }
+
+    /// Returns the first address with the given id, or None if there is no such address.
+    pub fn find_by_id(&self, id: &str) -> Option<&address::Address> {
+        self.addresses.iter().find(|addr| addr.id == id)
+    }
+
+    /// Builds an index from each id to the position of the first address with that id, for
+    /// repeated lookups in a large collection.
+    pub fn build_index(&self) -> HashMap<String, usize> {
+        let mut index = HashMap::new();
+        self.addresses.iter().enumerate().for_each(|(i, addr)| {
+            index.entry(addr.id.clone()).or_insert(i);
+        });
+        index
+    }
+
+    /// Returns the first address with the given numeric postal code using a binary search, or None
+    /// if there is no such address. The addresses must be sorted by postal code (see
+    /// is_sorted_by_postal), which is asserted in debug builds.
+    pub fn find_by_postal(&self, code: i32) -> Option<&address::Address> {
+        debug_assert!(
+            self.is_sorted_by_postal(),
+            "addresses must be sorted by postal code"
+        );
+
+        let index = self
+            .addresses
+            .partition_point(|addr| addr.numeric_postal_code().is_some_and(|c| c < code));
+        self.addresses
+            .get(index)
+            .filter(|addr| addr.numeric_postal_code() == Some(code))
+    }
+
+    /// Returns the longest common prefix of the numeric postal codes of the addresses with the given
+    /// country code, ignoring case, or None if the country has no numeric postal codes.
+    pub fn common_postal_prefix(&self, country_code: &str) -> Option<String> {
+        self.addresses
+            .iter()
+            .filter(|addr| addr.country_code().eq_ignore_ascii_case(country_code))
+            .filter(|addr| addr.numeric_postal_code().is_some())
+            .map(|addr| addr.postal_code().to_string())
+            .reduce(|prefix, code| {
+                let len = prefix
+                    .bytes()
+                    .zip(code.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                prefix[..len].to_string()
+            })
+    }
+
+    /// Returns the addresses as tab separated values with a header row of field names. Tabs, newlines
+    /// and backslashes in values are escaped so they don't break the columns.
+    pub fn to_tsv(&self) -> String {
+        let escape = |value: &str| {
+            value
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        };
+
+        let mut out = FIELD_NAMES.join("\t") + "\n";
+        self.addresses.iter().for_each(|addr| {
+            let row: Vec<String> = FIELD_NAMES
+                .iter()
+                .map(|name| escape(addr.field(name).unwrap_or_default()))
+                .collect();
+            out.push_str(&row.join("\t"));
+            out.push('\n');
+        });
+        out
+    }
+
+    /// Returns the GCD of a numeric attribute across the addresses, where the given closure extracts
+    /// the attribute from an address. Returns None if no address has the attribute.
+    pub fn numeric_field_gcd<F: Fn(&address::Address) -> Option<i32>>(
+        &self,
+        field: F,
+    ) -> Option<i32> {
+        self.addresses
+            .iter()
+            .filter_map(field)
+            .reduce(calculate_gcd)
+    }
+
+    /// Returns the addresses that have a province but no valid country.
+    pub fn province_without_country(&self) -> Vec<&address::Address> {
+        self.addresses
+            .iter()
+            .filter(|addr| addr.has_province_without_country())
+            .collect()
+    }
+
+    /// Splits the addresses into n contiguous shards whose lengths differ by at most one, with the
+    /// longer shards first. Returns fewer shards if n exceeds the number of addresses.
+    pub fn shard(&self, n: usize) -> Vec<&[address::Address]> {
+        let n = n.min(self.addresses.len());
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let (size, extra) = (self.addresses.len() / n, self.addresses.len() % n);
+        let mut shards = Vec::with_capacity(n);
+        let mut rest = self.addresses.as_slice();
+        for i in 0..n {
+            let (shard, tail) = rest.split_at(size + usize::from(i < extra));
+            shards.push(shard);
+            rest = tail;
+        }
+        shards
+    }
+
+    /// Returns the id pairs of addresses that have exactly the same coordinates. Addresses without
+    /// coordinates are skipped.
+    pub fn duplicate_coordinates(&self) -> Vec<(String, String)> {
+        let located: Vec<(&str, (f64, f64))> = self
+            .addresses
+            .iter()
+            .filter_map(|addr| addr.coordinates().map(|coords| (addr.id.as_str(), coords)))
+            .collect();
+
+        let mut pairs = Vec::new();
+        for (i, (id, coords)) in located.iter().enumerate() {
+            for (other_id, other_coords) in &located[i + 1..] {
+                if coords == other_coords {
+                    pairs.push((id.to_string(), other_id.to_string()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Returns the addresses whose numeric postal code satisfies the given predicate. Addresses with
+    /// a non-numeric postal code are skipped.
+    pub fn filter_postal<F: Fn(i32) -> bool>(&self, pred: F) -> Vec<&address::Address> {
+        self.addresses
+            .iter()
+            .filter(|addr| addr.numeric_postal_code().is_some_and(&pred))
+            .collect()
+    }
+
+    /// Left pads the numeric postal codes of all the addresses with zeros to the given width.
+    pub fn pad_all_postal_codes(&mut self, width: usize) {
+        self.addresses
+            .iter_mut()
+            .for_each(|addr| addr.pad_postal_code(width));
+    }
+
+    /// Swaps the address lines of every address whose lines look reversed (see
+    /// Address::normalize_line_order).
+    pub fn normalize_all_line_orders(&mut self) {
+        self.addresses
+            .iter_mut()
+            .for_each(address::Address::normalize_line_order);
+    }
+
+    /// Returns the id of each address with leading, trailing or internal whitespace other than
+    /// single spaces in a field, i.e. whitespace that Address::normalize would collapse, together
+    /// with the names of the affected fields (see FIELD_NAMES).
+    pub fn whitespace_issues(&self) -> Vec<(String, Vec<&'static str>)> {
+        let has_issue =
+            |value: &str| value.split_whitespace().collect::<Vec<_>>().join(" ") != value;
+        self.addresses
+            .iter()
+            .filter_map(|addr| {
+                let fields: Vec<&'static str> = FIELD_NAMES
+                    .iter()
+                    .copied()
+                    .filter(|name| addr.field(name).is_some_and(has_issue))
+                    .collect();
+                match fields.is_empty() {
+                    true => None,
+                    false => Some((addr.id.clone(), fields)),
+                }
+            })
+            .collect()
+    }
+
+    /// Builds a prefix index of the cities of the addresses for autocompletion.
+    pub fn build_city_index(&self) -> CityIndex {
+        let mut index = CityIndex::default();
+        self.addresses
+            .iter()
+            .for_each(|addr| index.insert(addr.city_or_town()));
+        index
+    }
+
+    /// Returns an error if the number of addresses doesn't match the expected count, e.g. from an
+    /// import manifest.
+    pub fn validate_count(&self, expected: usize) -> Result<(), String> {
+        match self.addresses.len() {
+            len if len == expected => Ok(()),
+            len => Err(format!("expected {} addresses but found {}", expected, len)),
+        }
+    }
+
+    /// Returns the addresses as comma separated values with only the given columns (see FIELD_NAMES)
+    /// in the given order, or an error if a column is unknown. Values containing commas, quotes or
+    /// newlines are quoted.
+    pub fn to_csv_columns(&self, columns: &[&str]) -> Result<String, String> {
+        if let Some(unknown) = columns.iter().find(|col| !FIELD_NAMES.contains(col)) {
+            return Err(format!("unknown column: {}", unknown));
+        }
+
+        let escape = |value: &str| match value.contains([',', '"', '\n', '\r']) {
+            true => format!("\"{}\"", value.replace('"', "\"\"")),
+            false => value.to_string(),
+        };
+
+        let mut out = columns.join(",") + "\n";
+        self.addresses.iter().for_each(|addr| {
+            let row: Vec<String> = columns
+                .iter()
+                .map(|col| escape(addr.field(col).unwrap_or_default()))
+                .collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        });
+        Ok(out)
+    }
+
+    /// Merges records of the same address, i.e. records that share an id and have a similarity of
+    /// at least 0.5, into the position of the first record. Fields are filled in from the more
+    /// recently updated record first. Returns the number of records merged away.
+    pub fn coalesce_duplicates(&mut self) -> usize {
+        let before = self.addresses.len();
+        let mut kept: Vec<address::Address> = Vec::with_capacity(before);
+        for addr in self.addresses.drain(..) {
+            let duplicate = kept
+                .iter()
+                .position(|k| k.id == addr.id && k.similarity(&addr) >= COALESCE_SIMILARITY);
+            match duplicate {
+                Some(i) => {
+                    let existing = kept.remove(i);
+                    kept.insert(i, existing.coalesce(addr));
+                }
+                None => kept.push(addr),
+            }
+        }
+        self.addresses = kept;
+        before - self.addresses.len()
+    }
+
+    /// Returns the addresses as a Markdown table with the id, city, province, postal code and country
+    /// of each address. Pipe characters in values are escaped.
+    pub fn to_markdown_table(&self) -> String {
+        let columns = ["id", "city", "province", "postal_code", "country"];
+        let mut out = String::from("| ID | City | Province | Postal Code | Country |\n");
+        out.push_str("| --- | --- | --- | --- | --- |\n");
+        self.addresses.iter().for_each(|addr| {
+            let row: Vec<String> = columns
+                .iter()
+                .map(|col| addr.field(col).unwrap_or_default().replace('|', "\\|"))
+                .collect();
+            out.push_str(&format!("| {} |\n", row.join(" | ")));
+        });
+        out
+    }
+
+    /// Returns the number of valid and invalid addresses and how often each validation error
+    /// occurred.
+    pub fn validation_summary(&self) -> ValidationSummary {
+        let mut summary = ValidationSummary {
+            total: self.addresses.len(),
+            ..Default::default()
+        };
         self.addresses.iter().for_each(|addr| {
             let errs = addr.validate();
-            if !errs.is_empty() {
-                err_strings.push(format!(
-                    "Address for ID: {} is invalid. Validation errors: {:?}",
-                    addr.id, errs
-                ));
+            match errs.is_empty() {
+                true => summary.valid += 1,
+                false => summary.invalid += 1,
             }
+            errs.into_iter()
+                .for_each(|err| *summary.error_counts.entry(err).or_default() += 1);
+        });
+        summary
+    }
+
+    /// Returns the sorted country codes of the countries whose addresses are all invalid. Addresses
+    /// without a country code are not counted as a country.
+    pub fn countries_with_no_valid_address(&self) -> Vec<String> {
+        let mut has_valid: BTreeMap<&str, bool> = BTreeMap::new();
+        self.addresses
+            .iter()
+            .filter(|addr| !addr.country_code().is_empty())
+            .for_each(|addr| *has_valid.entry(addr.country_code()).or_default() |= addr.is_valid());
+        has_valid
+            .into_iter()
+            .filter(|(_, valid)| !valid)
+            .map(|(code, _)| code.to_string())
+            .collect()
+    }
+
+    /// Groups the ids of invalid addresses by the validation errors they exhibit.
+    pub fn ids_by_error(&self) -> HashMap<ValidationError, Vec<String>> {
+        let mut ids: HashMap<ValidationError, Vec<String>> = HashMap::new();
+        self.addresses.iter().for_each(|addr| {
+            addr.validate()
+                .into_iter()
+                .for_each(|err| ids.entry(err).or_default().push(addr.id.clone()));
+        });
+        ids
+    }
+
+    /// Returns each validation error of every address paired with the pretty printing string of the
+    /// address, in address order.
+    pub fn errors_with_context(&self) -> Vec<(String, ValidationError)> {
+        self.addresses
+            .iter()
+            .flat_map(|addr| {
+                let context = addr.to_string();
+                addr.validate()
+                    .into_iter()
+                    .map(move |err| (context.clone(), err))
+            })
+            .collect()
+    }
+
+    /// Returns the addresses whose only validation error is the given rule.
+    pub fn failing_only(&self, rule: ValidationError) -> Vec<&address::Address> {
+        self.addresses
+            .iter()
+            .filter(|addr| addr.validate() == [rule])
+            .collect()
+    }
+
+    /// Returns the addresses whose postal code length differs from the most common postal code
+    /// length in their country. Countries without a single most common length are skipped.
+    pub fn postal_length_outliers(&self) -> Vec<&address::Address> {
+        let mut counts: HashMap<&str, HashMap<usize, usize>> = HashMap::new();
+        self.addresses.iter().for_each(|addr| {
+            *counts
+                .entry(addr.country_code())
+                .or_default()
+                .entry(addr.postal_code().len())
+                .or_default() += 1;
+        });
+
+        let modes: HashMap<&str, usize> = counts
+            .into_iter()
+            .filter_map(|(code, lens)| {
+                let max = lens.values().copied().max()?;
+                let mut modal = lens.into_iter().filter(|(_, count)| *count == max);
+                match (modal.next(), modal.next()) {
+                    (Some((len, _)), None) => Some((code, len)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        self.addresses
+            .iter()
+            .filter(|addr| {
+                modes
+                    .get(addr.country_code())
+                    .is_some_and(|len| *len != addr.postal_code().len())
+            })
+            .collect()
+    }
+
+    /// Returns a tree view of the addresses grouped by country and then province, listing the city
+    /// and id of each address. Countries and provinces are sorted by name.
+    pub fn to_tree_string(&self) -> String {
+        let mut tree: BTreeMap<&str, BTreeMap<&str, Vec<&address::Address>>> = BTreeMap::new();
+        self.addresses.iter().for_each(|addr| {
+            let country = match addr.country_name() {
+                "" => UNKNOWN_COUNTRY,
+                name => name,
+            };
+            let province = match addr.province_name() {
+                "" => UNKNOWN_PROVINCE,
+                name => name,
+            };
+            tree.entry(country)
+                .or_default()
+                .entry(province)
+                .or_default()
+                .push(addr);
         });
-        err_strings
+
+        let mut out = String::new();
+        for (country, provinces) in tree {
+            out.push_str(&format!("{country}\n"));
+            for (i, (province, addrs)) in provinces.iter().enumerate() {
+                let last_province = i == provinces.len() - 1;
+                let (branch, indent) = match last_province {
+                    true => ("└── ", "    "),
+                    false => ("├── ", "│   "),
+                };
+                out.push_str(&format!("{branch}{province}\n"));
+                for (j, addr) in addrs.iter().enumerate() {
+                    let leaf = match j == addrs.len() - 1 {
+                        true => "└── ",
+                        false => "├── ",
+                    };
+                    out.push_str(&format!(
+                        "{indent}{leaf}{} ({})\n",
+                        addr.city_or_town(),
+                        addr.id
+                    ));
+                }
+            }
+        }
+        out
     }
 
     /// Passes an Addresses instance to a given closure. Used as a helper function for unit tests.
-    pub fn with_addresses<F>(run: F)
+    #[cfg(test)]
+    pub(crate) fn with_addresses<F>(run: F)
     where
         F: Fn(Addresses),
     {
@@ -52,7 +1094,162 @@ impl Addresses {
 
 #[cfg(test)]
 mod tests {
-    use super::Addresses;
+    use std::collections::{HashMap, HashSet};
+
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+
+    use crate::address::{Address, ValidationError};
+
+    use super::{
+        calculate_gcd, diff_collections, postal_gcd_across, AddressQuery, AddressSortKey,
+        Addresses, CollectionDiff, ValidationSummary, JSON_FILE_PATH,
+    };
+
+    /// Returns the ids of the given addresses.
+    fn ids<'a>(addrs: &[&'a Address]) -> Vec<&'a str> {
+        addrs.iter().map(|addr| addr.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_iteration() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.len(), 3);
+            assert!(!addrs.is_empty());
+            assert_eq!(addrs.iter().count(), 3);
+            assert_eq!((&addrs).into_iter().count(), 3);
+            assert_eq!(addrs[1].id, "2");
+
+            let mut count = 0;
+            for addr in &addrs {
+                assert_eq!(addr.id, (count + 1).to_string());
+                count += 1;
+            }
+            assert_eq!(count, 3);
+
+            let owned: Vec<Address> = addrs.into_iter().collect();
+            assert_eq!(owned.len(), 3);
+        });
+
+        let addrs = Addresses::from(vec![Address::with_overrides(json!({}))]);
+        assert_eq!(addrs.len(), 1);
+        assert!(Addresses::from(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_from_json_str() {
+        let addrs = Addresses::from_json_str(
+            r#"[
+                {
+                    "id": "1",
+                    "type": { "code": "1", "name": "Physical Address" },
+                    "addressLineDetail": { "line1": "12 Long Street", "line2": "" },
+                    "provinceOrState": { "code": "WC", "name": "Western Cape" },
+                    "cityOrTown": "Cape Town",
+                    "country": { "code": "ZA", "name": "South Africa" },
+                    "postalCode": "8001",
+                    "lastUpdated": "2020-01-01T00:00:00.000Z"
+                },
+                {
+                    "id": "2",
+                    "type": { "code": "2", "name": "Postal Address" },
+                    "cityOrTown": "Beirut",
+                    "country": { "code": "LB", "name": "Lebanon" },
+                    "postalCode": "1107",
+                    "lastUpdated": "2021-01-01T00:00:00.000Z"
+                }
+            ]"#,
+        )
+        .expect("error parsing addresses");
+        assert_eq!(addrs.addresses.len(), 2);
+        assert_eq!(
+            addrs.addresses[0].to_string(),
+            "Physical Address: 12 Long Street - Cape Town - Western Cape - 8001 - South Africa"
+        );
+        assert_eq!(addrs.addresses[1].city_or_town(), "Beirut");
+
+        assert!(Addresses::from_json_str("not json")
+            .is_err_and(|err| err.starts_with("error deserializing json string: ")));
+    }
+
+    #[test]
+    fn test_to_json_file() {
+        let path = std::env::temp_dir().join(format!("addresses_{}.json", std::process::id()));
+        let path = path.to_str().expect("temp path is not valid unicode");
+        Addresses::with_addresses(|addrs| {
+            addrs.to_json_file(path).expect("error writing addresses");
+            let reloaded = Addresses::from_json_file(path).expect("error reading addresses");
+            assert_eq!(reloaded.len(), addrs.len());
+            assert_eq!(
+                ids(&reloaded.iter().collect::<Vec<_>>()),
+                ids(&addrs.iter().collect::<Vec<_>>())
+            );
+            assert_eq!(
+                addrs.to_json_string(),
+                Ok(std::fs::read_to_string(path).expect("error reading addresses"))
+            );
+        });
+        std::fs::remove_file(path).expect("error removing temp file");
+
+        let addrs = Addresses { addresses: vec![] };
+        assert_eq!(addrs.to_json_string(), Ok("[]".to_string()));
+        assert!(addrs
+            .to_json_file("/nonexistent/dir/addresses.json")
+            .is_err_and(|err| err.starts_with("error writing json file: ")));
+    }
+
+    #[test]
+    fn test_write_json_by_country() {
+        let dir = std::env::temp_dir().join(format!("addresses_by_country_{}", std::process::id()));
+        let dir = dir.to_str().expect("temp dir is not valid unicode");
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({
+                    "id": "2",
+                    "country": { "code": "LB", "name": "Lebanon" },
+                })),
+                Address::with_overrides(json!({ "id": "3", "country": { "name": "Nowhere" } })),
+                Address::with_overrides(json!({ "id": "4" })),
+                Address::with_overrides(json!({ "id": "5", "country": { "code": "za" } })),
+                Address::with_overrides(json!({ "id": "6", "country": { "code": "../x" } })),
+            ],
+        };
+
+        let paths = addrs
+            .write_json_by_country(dir)
+            .expect("error writing addresses");
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/LB.json", dir),
+                format!("{}/ZA.json", dir),
+                format!("{}/unknown.json", dir),
+            ]
+        );
+        let za = Addresses::from_json_file(&paths[1]).expect("error reading addresses");
+        assert_eq!(ids(&za.iter().collect::<Vec<_>>()), vec!["1", "4", "5"]);
+        let unknown = Addresses::from_json_file(&paths[2]).expect("error reading addresses");
+        assert_eq!(ids(&unknown.iter().collect::<Vec<_>>()), vec!["3", "6"]);
+
+        std::fs::remove_dir_all(dir).expect("error removing temp dir");
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let data = std::fs::read(JSON_FILE_PATH).expect("error reading addresses");
+        let addrs =
+            Addresses::from_reader(std::io::Cursor::new(data)).expect("error parsing addresses");
+        Addresses::with_addresses(|expected| {
+            assert_eq!(addrs.addresses.len(), expected.addresses.len());
+            assert_eq!(
+                addrs.addresses[2].to_string(),
+                expected.addresses[2].to_string()
+            );
+        });
+
+        assert!(Addresses::from_reader(std::io::Cursor::new(b"[{")).is_err());
+    }
 
     #[test]
     fn test_validate_addresses() {
@@ -65,4 +1262,1132 @@ mod tests {
                 ]);
         })
     }
+
+    #[test]
+    fn test_stream_validation() {
+        Addresses::with_addresses(|addrs| {
+            let mut out = Vec::new();
+            addrs
+                .stream_validation(&mut out)
+                .expect("error streaming validation");
+            let out = String::from_utf8(out).expect("error decoding validation output");
+            let lines: Vec<String> = out.lines().map(str::to_string).collect();
+            assert_eq!(lines, addrs.validate_addresses());
+            assert_eq!(lines.len(), 2);
+        })
+    }
+
+    #[test]
+    fn test_ids_by_error() {
+        Addresses::with_addresses(|addrs| {
+            let ids = addrs.ids_by_error();
+            assert_eq!(ids.len(), 2);
+            assert_eq!(ids[&ValidationError::InvalidLineDetail], vec!["2"]);
+            assert_eq!(ids[&ValidationError::MissingProvince], vec!["3"]);
+        })
+    }
+
+    #[test]
+    fn test_to_redacted_json() {
+        Addresses::with_addresses(|addrs| {
+            let json = addrs.to_redacted_json().expect("error redacting addresses");
+            assert!(!json.contains("Address 1"));
+            assert!(!json.contains("Line 2"));
+            assert!(!json.contains("Address 3"));
+            assert!(!json.contains("\"id\": \"1\""));
+            assert!(json.contains("\"id\": \"address-1\""));
+            assert!(json.contains("City 1"));
+            assert!(json.contains("Eastern Cape"));
+            assert!(json.contains("South Africa"));
+        })
+    }
+
+    #[test]
+    fn test_count_by_type() {
+        Addresses::with_addresses(|addrs| {
+            let counts = addrs.count_by_type();
+            assert_eq!(counts.len(), 3);
+            assert_eq!(counts["Physical Address"], 1);
+            assert_eq!(counts["Postal Address"], 1);
+            assert_eq!(counts["Business Address"], 1);
+        })
+    }
+
+    #[test]
+    fn test_group_by_type() {
+        Addresses::with_addresses(|addrs| {
+            let groups = addrs.group_by_type();
+            assert_eq!(groups.len(), 3);
+            assert_eq!(ids(&groups["Physical Address"]), vec!["1"]);
+            assert_eq!(ids(&groups["Postal Address"]), vec!["2"]);
+            assert_eq!(ids(&groups["Business Address"]), vec!["3"]);
+        });
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1", "type": {} })),
+                Address::with_overrides(json!({ "id": "2" })),
+                Address::with_overrides(json!({ "id": "3", "type": {} })),
+            ],
+        };
+        let groups = addrs.group_by_type();
+        assert_eq!(ids(&groups["Not available"]), vec!["1", "3"]);
+        assert_eq!(ids(&groups["Physical Address"]), vec!["2"]);
+    }
+
+    #[test]
+    fn test_type_distribution() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.type_distribution(),
+                vec![
+                    ("Business Address".to_string(), 1.0 / 3.0),
+                    ("Physical Address".to_string(), 1.0 / 3.0),
+                    ("Postal Address".to_string(), 1.0 / 3.0),
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn test_quality_grade() {
+        Addresses::with_addresses(|addrs| assert_eq!(addrs.quality_grade(), 'F'));
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(json!({
+                "suburbOrDistrict": "Suburb 1",
+            }))],
+        };
+        assert_eq!(addrs.quality_grade(), 'A');
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(json!({}))],
+        };
+        assert_eq!(addrs.quality_grade(), 'B');
+        assert_eq!(Addresses { addresses: vec![] }.quality_grade(), 'F');
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(json!({ "currency": "USD" }))],
+        };
+        assert_eq!(addrs.quality_grade(), 'F');
+    }
+
+    #[test]
+    fn test_flag_postal_country_conflicts() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2", "postalCode": "SW1A 1AA" })),
+            ],
+        };
+        assert_eq!(
+            addrs.flag_postal_country_conflicts(),
+            vec!["Address for ID: 2 has a postal code (SW1A 1AA) that looks like GB but its country is ZA"]
+        );
+    }
+
+    #[test]
+    fn test_future_dated() {
+        let now = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .expect("error parsing now")
+            .with_timezone(&Utc);
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(
+                    json!({ "id": "2", "lastUpdated": "2999-01-01T00:00:00.000Z" }),
+                ),
+                Address::with_overrides(json!({ "id": "3", "lastUpdated": "not a date" })),
+            ],
+        };
+        assert_eq!(ids(&addrs.future_dated(now)), vec!["2"]);
+    }
+
+    #[test]
+    fn test_most_similar_to() {
+        Addresses::with_addresses(|addrs| {
+            let query = Address::with_overrides(json!({
+                "addressLineDetail": { "line1": "Address 3" },
+                "provinceOrState": {},
+                "cityOrTown": "City 3",
+                "postalCode": "3456"
+            }));
+            let similar = addrs.most_similar_to(&query, 2);
+            assert_eq!(similar.len(), 2);
+            assert_eq!(similar[0].0.id, "3");
+            assert_eq!(similar[0].1, 1.0);
+            assert!(similar[1].1 < similar[0].1);
+            assert_eq!(addrs.most_similar_to(&query, 10).len(), 3);
+        })
+    }
+
+    #[test]
+    fn test_dedup_fuzzy() {
+        let mut addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({
+                    "id": "1",
+                    "addressLineDetail": { "line1": "12 Main Road", "line2": "Unit 4" }
+                })),
+                Address::with_overrides(json!({
+                    "id": "2",
+                    "addressLineDetail": { "line1": "12 main road", "line2": "" }
+                })),
+                Address::with_overrides(json!({
+                    "id": "3",
+                    "addressLineDetail": { "line1": "7 Long Street", "line2": "" },
+                    "cityOrTown": "Cape Town",
+                    "postalCode": "8001"
+                })),
+            ],
+        };
+        assert_eq!(addrs.dedup_fuzzy(0.9), 1);
+        assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["1", "3"]);
+    }
+
+    #[test]
+    fn test_invalid_to_json() {
+        Addresses::with_addresses(|addrs| {
+            let json = addrs
+                .invalid_to_json()
+                .expect("error serializing invalid addresses");
+            let values: Vec<serde_json::Value> =
+                serde_json::from_str(&json).expect("error deserializing invalid addresses");
+            assert_eq!(values.len(), 2);
+            assert_eq!(values[0]["id"], "2");
+            assert_eq!(
+                values[0]["validationErrors"],
+                json!([
+                    "You must include valid address details (line 1 and/or 2 must be filled in)"
+                ])
+            );
+            assert_eq!(values[1]["id"], "3");
+            assert_eq!(
+                values[1]["validationErrors"],
+                json!(["You must include a province if your country is ZA"])
+            );
+            assert!(!json.contains("Address 1"));
+        })
+    }
+
+    #[test]
+    fn test_round_robin_by_country() {
+        let lebanon = json!({ "code": "LB", "name": "Lebanon" });
+        let usa = json!({ "code": "US", "name": "United States" });
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2" })),
+                Address::with_overrides(json!({ "id": "3", "country": lebanon })),
+                Address::with_overrides(json!({ "id": "4", "country": usa })),
+                Address::with_overrides(json!({ "id": "5" })),
+                Address::with_overrides(json!({ "id": "6", "country": lebanon })),
+                Address::with_overrides(json!({ "id": "7", "country": { "code": "lb" } })),
+            ],
+        };
+        assert_eq!(
+            ids(&addrs.round_robin_by_country()),
+            vec!["1", "3", "4", "2", "6", "5", "7"]
+        );
+    }
+
+    #[test]
+    fn test_diff_collections() {
+        let old = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2" })),
+                Address::with_overrides(json!({ "id": "3" })),
+            ],
+        };
+        let new = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "3", "postalCode": "4321" })),
+                Address::with_overrides(json!({ "id": "4" })),
+            ],
+        };
+        assert_eq!(
+            diff_collections(&old, &new),
+            CollectionDiff {
+                added: vec!["4".to_string()],
+                removed: vec!["2".to_string()],
+                modified: vec!["3".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_postal_grid_spacing() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.postal_grid_spacing(), Some(1111));
+        });
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "1234" })),
+                Address::with_overrides(json!({ "postalCode": "abcd" })),
+            ],
+        };
+        assert_eq!(addrs.postal_grid_spacing(), None);
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "0" })),
+                Address::with_overrides(json!({ "postalCode": "2147483647" })),
+            ],
+        };
+        assert_eq!(addrs.postal_grid_spacing(), Some(i32::MAX));
+    }
+
+    #[test]
+    fn test_sample() {
+        let addrs = Addresses {
+            addresses: (0..20)
+                .map(|i| Address::with_overrides(json!({ "id": i.to_string() })))
+                .collect(),
+        };
+
+        let sample = ids(&addrs.sample(5, 42));
+        assert_eq!(sample.len(), 5);
+        assert_eq!(sample, ids(&addrs.sample(5, 42)));
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 5);
+        assert_eq!(addrs.sample(50, 7).len(), 20);
+        assert!(addrs.sample(0, 7).is_empty());
+    }
+
+    #[test]
+    fn test_validation_summary() {
+        Addresses::with_addresses(|addrs| {
+            let summary = addrs.validation_summary();
+            assert_eq!(summary.total, 3);
+            assert_eq!(summary.valid, 1);
+            assert_eq!(summary.invalid, 2);
+            assert_eq!(
+                summary.error_counts,
+                HashMap::from([
+                    (ValidationError::InvalidLineDetail, 1),
+                    (ValidationError::MissingProvince, 1),
+                ])
+            );
+        });
+
+        assert_eq!(
+            Addresses { addresses: vec![] }.validation_summary(),
+            ValidationSummary::default()
+        );
+    }
+
+    #[test]
+    fn test_countries_with_no_valid_address() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.countries_with_no_valid_address(), vec!["LB"]);
+        });
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2", "provinceOrState": {} })),
+                Address::with_overrides(json!({
+                    "id": "3",
+                    "country": { "code": "US", "name": "United States" },
+                    "postalCode": "123",
+                })),
+                Address::with_overrides(json!({ "id": "4", "country": { "name": "Nowhere" } })),
+                Address::with_overrides(json!({ "id": "5", "country": {} })),
+            ],
+        };
+        assert_eq!(addrs.countries_with_no_valid_address(), vec!["US"]);
+    }
+
+    #[test]
+    fn test_errors_with_context() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.errors_with_context(),
+                vec![
+                    (
+                        addrs.addresses[1].to_string(),
+                        ValidationError::InvalidLineDetail
+                    ),
+                    (
+                        addrs.addresses[2].to_string(),
+                        ValidationError::MissingProvince
+                    ),
+                ]
+            );
+            assert_eq!(
+                addrs.errors_with_context()[0].0,
+                "Postal Address: Not available - City 2 - Not available - 2345 - Lebanon"
+            );
+        })
+    }
+
+    #[test]
+    fn test_most_recently_updated() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(
+                    json!({ "id": "2", "lastUpdated": "2020-01-01T00:00:00Z" }),
+                ),
+                Address::with_overrides(
+                    json!({ "id": "3", "lastUpdated": "2020-01-01T00:00:00Z" }),
+                ),
+                Address::with_overrides(json!({ "id": "4", "lastUpdated": "not a date" })),
+            ],
+        };
+        assert_eq!(
+            addrs.most_recently_updated().map(|addr| addr.id.as_str()),
+            Some("2")
+        );
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(json!({ "lastUpdated": "" }))],
+        };
+        assert!(addrs.most_recently_updated().is_none());
+    }
+
+    #[test]
+    fn test_is_sorted_by_postal() {
+        let with_codes = |codes: &[&str]| Addresses {
+            addresses: codes
+                .iter()
+                .map(|code| Address::with_overrides(json!({ "postalCode": code })))
+                .collect(),
+        };
+
+        Addresses::with_addresses(|addrs| assert!(addrs.is_sorted_by_postal()));
+        assert!(with_codes(&["0999", "1000", "1000", "8001"]).is_sorted_by_postal());
+        assert!(!with_codes(&["8001", "1000"]).is_sorted_by_postal());
+        assert!(!with_codes(&["1000", "A100", "2000"]).is_sorted_by_postal());
+        assert!(with_codes(&["1000", "2000", "A100", "B100"]).is_sorted_by_postal());
+        assert!(!with_codes(&["1000", "B100", "A100"]).is_sorted_by_postal());
+        assert!(!with_codes(&["A100", "1000"]).is_sorted_by_postal());
+        assert!(with_codes(&["1000", ""]).is_sorted_by_postal());
+        assert!(with_codes(&[]).is_sorted_by_postal());
+    }
+
+    #[test]
+    fn test_filter_by_country() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(ids(&addrs.filter_by_country("ZA")), vec!["1", "3"]);
+            assert_eq!(ids(&addrs.filter_by_country("za")), vec!["1", "3"]);
+            assert_eq!(ids(&addrs.filter_by_country("LB")), vec!["2"]);
+            assert!(addrs.filter_by_country("US").is_empty());
+        })
+    }
+
+    #[test]
+    fn test_group_by_country() {
+        Addresses::with_addresses(|addrs| {
+            let groups = addrs.group_by_country();
+            assert_eq!(groups.len(), 2);
+            assert_eq!(ids(&groups["ZA"]), vec!["1", "3"]);
+            assert_eq!(ids(&groups["LB"]), vec!["2"]);
+        });
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1", "country": { "code": "za" } })),
+                Address::with_overrides(json!({ "id": "2", "country": { "name": "Nowhere" } })),
+            ],
+        };
+        let groups = addrs.group_by_country();
+        assert_eq!(ids(&groups["ZA"]), vec!["1"]);
+        assert_eq!(ids(&groups[""]), vec!["2"]);
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut addrs = Addresses {
+            addresses: [
+                ("1", "10000", "Durban"),
+                ("2", "9", "cape town"),
+                ("3", "B", "Cape Town"),
+                ("4", "0100", "Bloemfontein"),
+                ("5", "A1", "Cape Town"),
+            ]
+            .iter()
+            .map(|(id, code, city)| {
+                Address::with_overrides(json!({ "id": id, "postalCode": code, "cityOrTown": city }))
+            })
+            .collect(),
+        };
+
+        addrs.sort_by(AddressSortKey::PostalCode);
+        assert_eq!(
+            ids(&addrs.iter().collect::<Vec<_>>()),
+            vec!["2", "4", "1", "5", "3"]
+        );
+        addrs.sort_by(AddressSortKey::City);
+        assert_eq!(
+            ids(&addrs.iter().collect::<Vec<_>>()),
+            vec!["4", "5", "3", "1", "2"]
+        );
+
+        Addresses::with_addresses(|mut addrs| {
+            addrs.addresses.reverse();
+            addrs.sort_by(AddressSortKey::PostalCode);
+            assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["1", "2", "3"]);
+
+            addrs.addresses.reverse();
+            addrs.sort_by(AddressSortKey::City);
+            assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["1", "2", "3"]);
+
+            addrs.sort_by(AddressSortKey::Country);
+            assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["2", "1", "3"]);
+
+            addrs.addresses.reverse();
+            addrs.sort_by(AddressSortKey::LastUpdated);
+            assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["1", "2", "3"]);
+        });
+    }
+
+    #[test]
+    fn test_query() {
+        Addresses::with_addresses(|addrs| {
+            let za = AddressQuery {
+                country: Some("za".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(ids(&addrs.query(&za)), vec!["1", "3"]);
+
+            let valid_za = AddressQuery {
+                valid_only: true,
+                ..za.clone()
+            };
+            assert_eq!(ids(&addrs.query(&valid_za)), vec!["1"]);
+
+            let business = AddressQuery {
+                type_name: Some("Business Address".to_string()),
+                postal_range: Some((3000, 4000)),
+                ..za
+            };
+            assert_eq!(ids(&addrs.query(&business)), vec!["3"]);
+
+            let range = AddressQuery {
+                postal_range: Some((1000, 2999)),
+                ..Default::default()
+            };
+            assert_eq!(ids(&addrs.query(&range)), vec!["1", "2"]);
+            assert_eq!(addrs.query(&AddressQuery::default()).len(), 3);
+        })
+    }
+
+    #[test]
+    fn test_normalize_all_line_orders() {
+        let mut addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({
+                    "addressLineDetail": { "line1": "Sea Point Court", "line2": "12 Main Road" },
+                })),
+                Address::with_overrides(json!({})),
+            ],
+        };
+        addrs.normalize_all_line_orders();
+        assert_eq!(
+            addrs[0].to_string(),
+            "Physical Address: 12 Main Road, Sea Point Court - City 1 - Eastern Cape - 1234 - South Africa"
+        );
+        assert_eq!(
+            addrs[1].to_string(),
+            Address::with_overrides(json!({})).to_string()
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        Addresses::with_addresses(|mut addrs| {
+            addrs.merge(
+                Addresses::from_json_file(JSON_FILE_PATH).expect("error fetching addresses"),
+            );
+            assert_eq!(addrs.len(), 6);
+            assert_eq!(
+                ids(&addrs.iter().collect::<Vec<_>>()),
+                vec!["1", "2", "3", "1", "2", "3"]
+            );
+
+            addrs.merge(Addresses { addresses: vec![] });
+            assert_eq!(addrs.len(), 6);
+        });
+    }
+
+    #[test]
+    fn test_merge_dedup() {
+        let mut addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2", "postalCode": "2000" })),
+            ],
+        };
+        let other = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "10" })),
+                Address::with_overrides(json!({ "id": "2", "postalCode": "3000" })),
+                Address::with_overrides(json!({ "id": "11", "postalCode": "4000" })),
+                Address::with_overrides(json!({ "id": "12", "postalCode": "4000" })),
+            ],
+        };
+
+        addrs.merge_dedup(other);
+        assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["1", "2", "11"]);
+        assert_eq!(addrs[1].postal_code(), "2000");
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2", "postalCode": "4321" })),
+                Address::with_overrides(json!({ "id": "3" })),
+                Address::with_overrides(
+                    json!({ "id": "4", "lastUpdated": "2020-01-01T00:00:00Z" }),
+                ),
+            ],
+        };
+        let pairs: Vec<(&str, &str)> = addrs
+            .duplicates()
+            .iter()
+            .map(|(first, dup)| (first.id.as_str(), dup.id.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("1", "3"), ("1", "4")]);
+
+        addrs.dedup();
+        assert_eq!(ids(&addrs.iter().collect::<Vec<_>>()), vec!["1", "2"]);
+        assert!(addrs.duplicates().is_empty());
+
+        Addresses::with_addresses(|mut addrs| {
+            addrs.dedup();
+            assert_eq!(addrs.len(), 3);
+        });
+    }
+
+    #[test]
+    fn test_find_by_id() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.find_by_id("1").map(|addr| addr.to_string()),
+                Some(addrs.addresses[0].to_string())
+            );
+            assert!(addrs.find_by_id("missing").is_none());
+        });
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1", "postalCode": "1000" })),
+                Address::with_overrides(json!({ "id": "1", "postalCode": "2000" })),
+            ],
+        };
+        assert_eq!(
+            addrs.find_by_id("1").map(|addr| addr.postal_code()),
+            Some("1000")
+        );
+    }
+
+    #[test]
+    fn test_build_index() {
+        Addresses::with_addresses(|addrs| {
+            let index = addrs.build_index();
+            assert_eq!(index.len(), 3);
+            assert_eq!(index.get("1"), Some(&0));
+            assert_eq!(index.get("3"), Some(&2));
+            assert_eq!(index.get("missing"), None);
+        });
+    }
+
+    #[test]
+    fn test_find_by_postal() {
+        let addrs = Addresses {
+            addresses: [("1", "0999"), ("2", "1000"), ("3", "1000"), ("4", "8001")]
+                .iter()
+                .map(|(id, code)| Address::with_overrides(json!({ "id": id, "postalCode": code })))
+                .collect(),
+        };
+
+        assert_eq!(
+            addrs.find_by_postal(999).map(|addr| addr.id.as_str()),
+            Some("1")
+        );
+        assert_eq!(
+            addrs.find_by_postal(1000).map(|addr| addr.id.as_str()),
+            Some("2")
+        );
+        assert_eq!(
+            addrs.find_by_postal(8001).map(|addr| addr.id.as_str()),
+            Some("4")
+        );
+        assert!(addrs.find_by_postal(5000).is_none());
+        assert!(addrs.find_by_postal(9000).is_none());
+        assert!(Addresses { addresses: vec![] }
+            .find_by_postal(1000)
+            .is_none());
+
+        let mut addrs = Addresses {
+            addresses: [("1", "8001"), ("2", "SW1A 1AA"), ("3", "0999"), ("4", "")]
+                .iter()
+                .map(|(id, code)| Address::with_overrides(json!({ "id": id, "postalCode": code })))
+                .collect(),
+        };
+        addrs.sort_by(AddressSortKey::PostalCode);
+        assert!(addrs.is_sorted_by_postal());
+        assert_eq!(
+            addrs.find_by_postal(8001).map(|addr| addr.id.as_str()),
+            Some("1")
+        );
+        assert!(addrs.find_by_postal(1234).is_none());
+    }
+
+    #[test]
+    fn test_common_postal_prefix() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "8001" })),
+                Address::with_overrides(json!({ "postalCode": "8005" })),
+                Address::with_overrides(json!({ "postalCode": "8012" })),
+                Address::with_overrides(json!({ "postalCode": "80A1" })),
+                Address::with_overrides(json!({
+                    "country": { "code": "US", "name": "United States" },
+                    "postalCode": "90210",
+                })),
+            ],
+        };
+        assert_eq!(addrs.common_postal_prefix("ZA"), Some("80".to_string()));
+        assert_eq!(addrs.common_postal_prefix("za"), Some("80".to_string()));
+        assert_eq!(addrs.common_postal_prefix("US"), Some("90210".to_string()));
+        assert_eq!(addrs.common_postal_prefix("GB"), None);
+
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.common_postal_prefix("ZA"), Some(String::new()));
+        });
+    }
+
+    #[test]
+    fn test_calculate_gcd() {
+        assert_eq!(calculate_gcd(11, 22), 11);
+        assert_eq!(calculate_gcd(0, 6), 6);
+    }
+
+    #[test]
+    fn test_to_tsv() {
+        Addresses::with_addresses(|addrs| {
+            let tsv = addrs.to_tsv();
+            let lines: Vec<&str> = tsv.lines().collect();
+            assert_eq!(lines.len(), 4);
+            assert_eq!(
+                lines[0],
+                "id\ttype\tline1\tline2\tline3\tsuburb\tcity\tprovince\tpostal_code\tcountry_code\tcountry\tlast_updated"
+            );
+            assert_eq!(
+                lines[1],
+                "1\tPhysical Address\tAddress 1\tLine 2\t\t\tCity 1\tEastern Cape\t1234\tZA\tSouth Africa\t2015-06-21T00:00:00.000Z"
+            );
+        });
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(
+                json!({ "cityOrTown": "City\t1\nNorth" }),
+            )],
+        };
+        assert_eq!(addrs.to_tsv().lines().count(), 2);
+        assert!(addrs.to_tsv().contains("\tCity\\t1\\nNorth\t"));
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(json!({
+                "addressLineDetail": { "line1": "Address 1", "line2": "Line 2", "line3": "Unit 4" }
+            }))],
+        };
+        assert!(addrs.to_tsv().contains("\tAddress 1\tLine 2\tUnit 4\t"));
+    }
+
+    #[test]
+    fn test_numeric_field_gcd() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "1200" })),
+                Address::with_overrides(json!({ "postalCode": "abcd" })),
+                Address::with_overrides(json!({ "postalCode": "1800" })),
+            ],
+        };
+        assert_eq!(
+            addrs.numeric_field_gcd(Address::numeric_postal_code),
+            Some(600)
+        );
+        assert_eq!(addrs.numeric_field_gcd(|_| None), None);
+    }
+
+    #[test]
+    fn test_province_without_country() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({ "id": "2", "country": { "code": "ZA" } })),
+                Address::with_overrides(json!({ "id": "3", "country": {}, "provinceOrState": {} })),
+            ],
+        };
+        assert_eq!(ids(&addrs.province_without_country()), vec!["2"]);
+    }
+
+    #[test]
+    fn test_shard() {
+        let addrs = Addresses {
+            addresses: (1..=10)
+                .map(|id| Address::with_overrides(json!({ "id": id.to_string() })))
+                .collect(),
+        };
+        let lens: Vec<usize> = addrs.shard(3).iter().map(|shard| shard.len()).collect();
+        assert_eq!(lens, vec![4, 3, 3]);
+        assert_eq!(addrs.shard(3)[1][0].id, "5");
+        assert_eq!(addrs.shard(20).len(), 10);
+        assert!(addrs.shard(0).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_coordinates() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(
+                    json!({ "id": "1", "latitude": -33.92, "longitude": 18.42 }),
+                ),
+                Address::with_overrides(
+                    json!({ "id": "2", "latitude": -26.2, "longitude": 28.05 }),
+                ),
+                Address::with_overrides(json!({ "id": "3" })),
+                Address::with_overrides(
+                    json!({ "id": "4", "latitude": -33.92, "longitude": 18.42 }),
+                ),
+            ],
+        };
+        assert_eq!(
+            addrs.duplicate_coordinates(),
+            vec![("1".to_string(), "4".to_string())]
+        );
+        Addresses::with_addresses(|addrs| assert!(addrs.duplicate_coordinates().is_empty()));
+    }
+
+    #[test]
+    fn test_filter_postal() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                ids(&addrs.filter_postal(|code| code % 2 == 0)),
+                vec!["1", "3"]
+            );
+            assert_eq!(ids(&addrs.filter_postal(|code| code > 3000)), vec!["3"]);
+        });
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "SW1A 1AA" })),
+                Address::with_overrides(json!({ "postalCode": "-1234" })),
+            ],
+        };
+        assert!(addrs.filter_postal(|_| true).is_empty());
+    }
+
+    #[test]
+    fn test_pad_all_postal_codes() {
+        let mut addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "12" })),
+                Address::with_overrides(json!({ "postalCode": "1234" })),
+            ],
+        };
+        addrs.pad_all_postal_codes(4);
+        let codes: Vec<&str> = addrs
+            .addresses
+            .iter()
+            .map(|addr| addr.postal_code())
+            .collect();
+        assert_eq!(codes, vec!["0012", "1234"]);
+    }
+
+    #[test]
+    fn test_whitespace_issues() {
+        Addresses::with_addresses(|addrs| assert!(addrs.whitespace_issues().is_empty()));
+
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1" })),
+                Address::with_overrides(json!({
+                    "id": "2",
+                    "addressLineDetail": { "line1": " Address 1", "line2": "Line  2" },
+                    "cityOrTown": "City 1 ",
+                })),
+                Address::with_overrides(json!({
+                    "id": "3",
+                    "cityOrTown": "Cape\tTown",
+                    "postalCode": "12\n34"
+                })),
+            ],
+        };
+        assert_eq!(
+            addrs.whitespace_issues(),
+            vec![
+                ("2".to_string(), vec!["line1", "line2", "city"]),
+                ("3".to_string(), vec!["city", "postal_code"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_city_index() {
+        Addresses::with_addresses(|addrs| {
+            let index = addrs.build_city_index();
+            assert_eq!(index.complete("Cit"), vec!["City 1", "City 2", "City 3"]);
+            assert_eq!(index.complete("city 2"), vec!["City 2"]);
+            assert!(index.complete("Town").is_empty());
+        })
+    }
+
+    #[test]
+    fn test_validate_count() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(addrs.validate_count(3), Ok(()));
+            assert_eq!(
+                addrs.validate_count(4),
+                Err("expected 4 addresses but found 3".to_string())
+            );
+        })
+    }
+
+    #[test]
+    fn test_to_csv_columns() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.to_csv_columns(&["id", "city", "country"]),
+                Ok("id,city,country\n\
+                    1,City 1,South Africa\n\
+                    2,City 2,Lebanon\n\
+                    3,City 3,South Africa\n"
+                    .to_string())
+            );
+            assert_eq!(
+                addrs.to_csv_columns(&["id", "town"]),
+                Err("unknown column: town".to_string())
+            );
+        });
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(
+                json!({ "cityOrTown": "Cape Town, \"CBD\"" }),
+            )],
+        };
+        assert_eq!(
+            addrs.to_csv_columns(&["city"]),
+            Ok("city\n\"Cape Town, \"\"CBD\"\"\"\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_coalesce_duplicates() {
+        let mut addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1", "provinceOrState": {} })),
+                Address::with_overrides(json!({ "id": "2", "cityOrTown": "City 2" })),
+                Address::with_overrides(json!({
+                    "id": "1",
+                    "addressLineDetail": {},
+                    "lastUpdated": "2016-06-21T00:00:00.000Z"
+                })),
+                Address::with_overrides(json!({
+                    "id": "2",
+                    "addressLineDetail": { "line1": "Elsewhere" },
+                    "provinceOrState": { "name": "Gauteng" },
+                    "cityOrTown": "Johannesburg",
+                    "postalCode": "2000"
+                })),
+            ],
+        };
+        assert_eq!(addrs.coalesce_duplicates(), 1);
+        assert_eq!(addrs.addresses.len(), 3);
+        assert_eq!(
+            format!("{}", addrs.addresses[0]),
+            "Physical Address: Address 1, Line 2 - City 1 - Eastern Cape - 1234 - South Africa"
+        );
+        assert!(addrs.addresses[0].validate().is_empty());
+    }
+
+    #[test]
+    fn test_to_markdown_table() {
+        Addresses::with_addresses(|addrs| {
+            let table = addrs.to_markdown_table();
+            let lines: Vec<&str> = table.lines().collect();
+            assert_eq!(lines.len(), 5);
+            assert_eq!(lines[0], "| ID | City | Province | Postal Code | Country |");
+            assert_eq!(lines[1], "| --- | --- | --- | --- | --- |");
+            assert_eq!(
+                lines[2],
+                "| 1 | City 1 | Eastern Cape | 1234 | South Africa |"
+            );
+            assert_eq!(lines[3], "| 2 | City 2 |  | 2345 | Lebanon |");
+        });
+
+        let addrs = Addresses {
+            addresses: vec![Address::with_overrides(json!({ "cityOrTown": "A|B" }))],
+        };
+        assert!(addrs.to_markdown_table().contains("| A\\|B |"));
+    }
+
+    #[test]
+    fn test_postal_gcd_across() {
+        let first = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "1200" })),
+                Address::with_overrides(json!({ "postalCode": "abcd" })),
+            ],
+        };
+        let second = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "postalCode": "1800" })),
+                Address::with_overrides(json!({ "postalCode": "3000" })),
+            ],
+        };
+        assert_eq!(postal_gcd_across(&[&first, &second]), Some(600));
+        assert_eq!(postal_gcd_across(&[&first]), Some(1200));
+        assert_eq!(postal_gcd_across(&[]), None);
+    }
+
+    #[test]
+    fn test_failing_only() {
+        Addresses::with_addresses(|addrs| {
+            let failing = addrs.failing_only(ValidationError::MissingProvince);
+            assert_eq!(failing.len(), 1);
+            assert_eq!(failing[0].id, "3");
+            assert!(addrs
+                .failing_only(ValidationError::InvalidPostalCode)
+                .is_empty());
+        })
+    }
+
+    #[test]
+    fn test_postal_length_outliers() {
+        let addrs = Addresses {
+            addresses: vec![
+                Address::with_overrides(json!({ "id": "1", "postalCode": "1234" })),
+                Address::with_overrides(json!({ "id": "2", "postalCode": "2345" })),
+                Address::with_overrides(json!({ "id": "3", "postalCode": "345678" })),
+                Address::with_overrides(json!({ "id": "4", "postalCode": "4567" })),
+                Address::with_overrides(json!({
+                    "id": "5",
+                    "country": { "code": "LB", "name": "Lebanon" },
+                    "postalCode": "56"
+                })),
+            ],
+        };
+        assert_eq!(ids(&addrs.postal_length_outliers()), vec!["3"]);
+    }
+
+    #[test]
+    fn test_to_tree_string() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.to_tree_string(),
+                [
+                    "Lebanon",
+                    "└── (unknown province)",
+                    "    └── City 2 (2)",
+                    "South Africa",
+                    "├── (unknown province)",
+                    "│   └── City 3 (3)",
+                    "└── Eastern Cape",
+                    "    └── City 1 (1)",
+                    "",
+                ]
+                .join("\n")
+            );
+        })
+    }
+
+    #[test]
+    fn test_from_json_file_verified() {
+        let checksum = "74c76a6e05dac724cfed30cfa65196ad70c5bd9405f637be349d15e1fea598ee";
+        let addrs = Addresses::from_json_file_verified(JSON_FILE_PATH, checksum)
+            .expect("error fetching verified addresses");
+        assert_eq!(addrs.addresses.len(), 3);
+        assert!(
+            Addresses::from_json_file_verified(JSON_FILE_PATH, &checksum.to_uppercase()).is_ok()
+        );
+
+        let wrong = "0".repeat(64);
+        assert_eq!(
+            Addresses::from_json_file_verified(JSON_FILE_PATH, &wrong).map(|_| ()),
+            Err(format!(
+                "checksum mismatch: expected {} but found {}",
+                wrong, checksum
+            ))
+        );
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn test_sorted_by_city_collated() {
+        let addrs = Addresses {
+            addresses: ["Zurich", "Åre", "Émile", "Berlin", "Eagle"]
+                .iter()
+                .enumerate()
+                .map(|(i, city)| {
+                    Address::with_overrides(json!({ "id": i.to_string(), "cityOrTown": city }))
+                })
+                .collect(),
+        };
+        let cities = |locale: &str| -> Vec<String> {
+            addrs
+                .sorted_by_city_collated(locale)
+                .iter()
+                .map(|addr| addr.city_or_town().to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            cities("en"),
+            vec!["Åre", "Berlin", "Eagle", "Émile", "Zurich"]
+        );
+        assert_eq!(
+            cities("sv"),
+            vec!["Berlin", "Eagle", "Émile", "Zurich", "Åre"]
+        );
+        assert_eq!(cities("not a locale"), cities("und"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_validate_addresses_parallel() {
+        Addresses::with_addresses(|addrs| {
+            assert_eq!(
+                addrs.validate_addresses_parallel(),
+                addrs.validate_addresses()
+            );
+        });
+
+        let addrs = Addresses {
+            addresses: (0..200)
+                .map(|i| {
+                    Address::with_overrides(json!({
+                        "id": i.to_string(),
+                        "postalCode": if i % 3 == 0 { "bad" } else { "1234" },
+                    }))
+                })
+                .collect(),
+        };
+        assert_eq!(
+            addrs.validate_addresses_parallel(),
+            addrs.validate_addresses()
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_from_json_gz_file() {
+        let addrs = Addresses::from_json_gz_file("src/addresses.json.gz")
+            .expect("error fetching gzipped addresses");
+        Addresses::with_addresses(|expected| {
+            assert_eq!(addrs.addresses.len(), expected.addresses.len());
+            assert_eq!(
+                format!("{}", addrs.addresses[0]),
+                format!("{}", expected.addresses[0])
+            );
+        });
+        assert!(Addresses::from_json_gz_file("src/addresses.json").is_err());
+    }
 }
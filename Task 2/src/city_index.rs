@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+/// A node of the city trie, keyed by lowercase characters.
+#[derive(Default, Debug)]
+struct Node {
+    children: BTreeMap<char, Node>,
+    cities: Vec<String>,
+}
+
+/// A prefix trie of city names for case-insensitive autocompletion.
+#[derive(Default, Debug)]
+pub struct CityIndex {
+    root: Node,
+}
+
+#[allow(dead_code)]
+impl CityIndex {
+    /// Adds a city name to the index. Empty and already indexed names are ignored.
+    pub fn insert(&mut self, city: &str) {
+        if city.is_empty() {
+            return;
+        }
+
+        let node = city.to_lowercase().chars().fold(&mut self.root, |node, c| {
+            node.children.entry(c).or_default()
+        });
+        if !node.cities.iter().any(|existing| existing == city) {
+            node.cities.push(city.to_string());
+        }
+    }
+
+    /// Returns the indexed city names that start with the prefix, ignoring case, in alphabetical
+    /// order.
+    pub fn complete(&self, prefix: &str) -> Vec<&str> {
+        let mut node = &self.root;
+        for c in prefix.to_lowercase().chars() {
+            match node.children.get(&c) {
+                Some(child) => node = child,
+                None => return Vec::new(),
+            }
+        }
+
+        let mut cities = Vec::new();
+        Self::collect(node, &mut cities);
+        cities
+    }
+
+    /// Adds the city names of the node and all its descendants to the cities vector.
+    fn collect<'a>(node: &'a Node, cities: &mut Vec<&'a str>) {
+        cities.extend(node.cities.iter().map(String::as_str));
+        node.children
+            .values()
+            .for_each(|child| Self::collect(child, cities));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CityIndex;
+
+    #[test]
+    fn test_complete() {
+        let mut index = CityIndex::default();
+        [
+            "Cape Town",
+            "Centurion",
+            "cape town",
+            "Durban",
+            "Cape Town",
+            "",
+        ]
+        .iter()
+        .for_each(|city| index.insert(city));
+        assert_eq!(index.complete("CAP"), vec!["Cape Town", "cape town"]);
+        assert_eq!(
+            index.complete("c"),
+            vec!["Cape Town", "cape town", "Centurion"]
+        );
+        assert_eq!(index.complete("Durban"), vec!["Durban"]);
+        assert!(index.complete("Joh").is_empty());
+        assert_eq!(index.complete("").len(), 4);
+    }
+}
@@ -1,2 +1,3 @@
-mod address;
-mod addresses;
+pub mod address;
+pub mod addresses;
+pub mod city_index;